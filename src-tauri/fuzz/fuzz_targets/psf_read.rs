@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shadps4_launcher_lib::file_format::psf::PSF;
+
+// Seed the corpus with the fixtures:
+// cargo +nightly fuzz run psf_read corpus/psf_read ../tests/fixtures/psf ../tests/fixtures/psf/malformed
+fuzz_target!(|data: &[u8]| {
+    let Ok(psf) = PSF::from_bytes(data) else {
+        return;
+    };
+    let mut out = Vec::new();
+    psf.write(&mut out).expect("parsed PSF should be writable");
+    // Nothing was changed, so the file is written back as it was read
+    assert_eq!(data, out.as_slice(), "unchanged PSF should round-trip");
+});
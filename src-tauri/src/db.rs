@@ -0,0 +1,16 @@
+use anyhow::anyhow;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+/// Database shared with the frontend, preloaded by the sql plugin
+pub const DB_URL: &str = "sqlite:data.db";
+
+pub async fn pool(app_handle: &AppHandle) -> anyhow::Result<SqlitePool> {
+    let instances = app_handle.state::<DbInstances>();
+    let instances = instances.0.read().await;
+    match instances.get(DB_URL) {
+        Some(DbPool::Sqlite(pool)) => Ok(pool.clone()),
+        _ => Err(anyhow!("database {} is not loaded", DB_URL)),
+    }
+}
@@ -19,6 +19,51 @@ pub fn gen_migrations() -> Vec<Migration> {
 CREATE UNIQUE INDEX games_path_idx ON games (path);
 "#,
             kind: MigrationKind::Up,
-        }
+        },
+        Migration {
+            version: 2,
+            description: "create library roots",
+            // language=sqlite
+            sql: r#"CREATE TABLE library_roots
+(
+    id        INTEGER PRIMARY KEY,
+    path      TEXT    NOT NULL,
+    max_depth INTEGER NOT NULL DEFAULT 3,
+    enabled   INTEGER NOT NULL DEFAULT 1,
+    read_only INTEGER NOT NULL DEFAULT 0
+);
+CREATE UNIQUE INDEX library_roots_path_idx ON library_roots (path);
+"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "add game stats and user data",
+            // language=sqlite
+            sql: r#"ALTER TABLE games ADD COLUMN play_time INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE games ADD COLUMN last_launched INTEGER;
+ALTER TABLE games ADD COLUMN launch_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE games ADD COLUMN favourite INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE games ADD COLUMN notes TEXT;
+
+CREATE TABLE game_tags
+(
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    tag     TEXT    NOT NULL,
+    PRIMARY KEY (game_id, tag)
+);
+
+CREATE TABLE game_sessions
+(
+    id          INTEGER PRIMARY KEY,
+    game_id     INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    started_at  INTEGER NOT NULL,
+    ended_at    INTEGER,
+    exit_status INTEGER
+);
+CREATE INDEX game_sessions_game_idx ON game_sessions (game_id);
+"#,
+            kind: MigrationKind::Up,
+        },
     ]
 }
\ No newline at end of file
@@ -1 +1,4 @@
+pub mod pfs;
+pub mod pkg;
 pub mod psf;
+pub mod trp;
@@ -0,0 +1,459 @@
+use crate::file_format::pfs::file_format::*;
+use aes::Aes128;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use flate2::read::ZlibDecoder;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use zerocopy::FromBytes;
+
+/// Based on
+/// https://github.com/shadps4-emu/shadPS4/blob/b41664ac616894686e072ede61c609b422d79ed4/src/core/file_format/pfs.h
+mod file_format {
+    use static_assertions::assert_eq_size;
+    use zerocopy::little_endian::{U16, U32, U64};
+    use zerocopy_derive::{FromBytes, Immutable, KnownLayout};
+
+    pub(super) const PFS_MODE_ENCRYPTED: u16 = 0x4;
+
+    /// Seed of the image keys, in the plain text part of the image
+    pub(super) const PFS_SEED_OFFSET: u64 = 0x370;
+    pub(super) const XTS_SECTOR_SIZE: u64 = 0x1000;
+
+    pub(super) const PFSC_MAGIC: u32 = 0x43534650;
+    pub(super) const PFSC_SEARCH_START: u64 = 0x20000;
+    pub(super) const PFSC_SEARCH_STEP: u64 = 0x10000;
+
+    /// Size of the blocks of the inner image, once decompressed
+    pub(super) const BLOCK_SIZE: u64 = 0x10000;
+    pub(super) const INODE_SIZE: usize = 0xA8;
+    pub(super) const INODES_PER_BLOCK: u64 = BLOCK_SIZE / INODE_SIZE as u64;
+
+    pub(super) const DIRENT_FILE: u32 = 2;
+    pub(super) const DIRENT_DIR: u32 = 3;
+
+    /// Superblock of both the outer image and the inner (PFSC) one.
+    /// The outer one is stored in plain text even when the image is encrypted
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct Superblock {
+        pub version: U64,
+        pub magic: U64,
+        pub id: U64,
+        pub fmode: u8,
+        pub clean: u8,
+        pub read_only: u8,
+        pub rsv: u8,
+        pub mode: U16,
+        pub unk1: U16,
+        pub block_size: U32,
+        pub nbackup: U32,
+        pub nblock: U64,
+        pub dinode_count: U64,
+        pub ndblock: U64,
+        pub dinode_block_count: U64,
+        pub superroot_ino: U64,
+    }
+    assert_eq_size!(Superblock, [u8; 0x50]);
+
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct PfscHeader {
+        pub magic: U32,
+        pub unk4: U32,
+        pub unk8: U32,
+        pub block_sz: U32,
+        pub block_sz2: U64,
+        pub block_offsets: U64,
+        pub data_start: U64,
+        pub data_length: U64,
+    }
+    assert_eq_size!(PfscHeader, [u8; 0x30]);
+
+    /// Stored every [`INODE_SIZE`] bytes, the rest of the slot is unused
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct Inode {
+        pub mode: U16,
+        pub nlink: U16,
+        pub flags: U32,
+        pub size: U64,
+        pub size_compressed: U64,
+        pub time_sec: [U64; 4],
+        pub time_nsec: [U32; 4],
+        pub uid: U32,
+        pub gid: U32,
+        pub unk1: U64,
+        pub unk2: U64,
+        pub blocks: U32,
+        pub loc: U32,
+    }
+    assert_eq_size!(Inode, [u8; 0x68]);
+
+    /// Followed by `namelen` bytes of name. The next one starts `entsize` bytes after this one
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct DirentHeader {
+        pub ino: U32,
+        pub kind: U32,
+        pub namelen: U32,
+        pub entsize: U32,
+    }
+    assert_eq_size!(DirentHeader, [u8; 0x10]);
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    #[error("PFS image is out of the file: offset={0:X}, size={1:X}")]
+    ImageOutOfBounds(u64, u64),
+    #[error("PFS image is encrypted and can't be read without the package keys")]
+    MissingKey,
+    #[error("PFSC header not found")]
+    PfscNotFound,
+    #[error("unsupported PFSC block size: {0:X}")]
+    UnsupportedBlockSize(u64),
+    #[error("block {0} is out of the image")]
+    BlockOutOfBounds(u64),
+    #[error("could not inflate block {0}: {1}")]
+    Inflate(u64, io::Error),
+    #[error("inode {0} is out of the inode table")]
+    InodeOutOfBounds(u64),
+    #[error("inode {0} has less blocks than its size")]
+    InodeTooSmall(u32),
+    #[error("invalid file name: {0:?}")]
+    InvalidName(String),
+    #[error("uroot directory not found")]
+    RootNotFound,
+    #[error("directory is listed twice: inode {0}")]
+    DirectoryLoop(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PfsFileKind {
+    File,
+    Dir,
+}
+
+#[derive(Debug, Clone)]
+pub struct PfsFile {
+    /// Relative to the image root, `/` separated
+    pub path: String,
+    pub kind: PfsFileKind,
+    pub size: u64,
+    ino: u32,
+}
+
+/// AES-XTS with the sector number as tweak
+struct Xts {
+    data: Aes128,
+    tweak: Aes128,
+}
+
+impl Xts {
+    fn new(ekpfs: &[u8; 32], seed: &[u8; 16]) -> Self {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(ekpfs).expect("HMAC accepts any key size");
+        mac.update(&1u32.to_le_bytes());
+        mac.update(seed);
+        let key = mac.finalize().into_bytes();
+        Self {
+            tweak: Aes128::new(key[..16].into()),
+            data: Aes128::new(key[16..].into()),
+        }
+    }
+
+    fn decrypt_sector(&self, sector: u64, buf: &mut [u8]) {
+        let mut tweak = [0u8; 16];
+        tweak[..8].copy_from_slice(&sector.to_le_bytes());
+        let mut tweak: aes::Block = tweak.into();
+        self.tweak.encrypt_block(&mut tweak);
+        for block in buf.chunks_exact_mut(16) {
+            block.iter_mut().zip(&tweak).for_each(|(b, t)| *b ^= t);
+            self.data.decrypt_block(aes::Block::from_mut_slice(block));
+            block.iter_mut().zip(&tweak).for_each(|(b, t)| *b ^= t);
+            // Multiply the tweak by x in GF(2^128)
+            let carry = tweak[15] >> 7;
+            for i in (1..16).rev() {
+                tweak[i] = (tweak[i] << 1) | (tweak[i - 1] >> 7);
+            }
+            tweak[0] = (tweak[0] << 1) ^ (carry * 0x87);
+        }
+    }
+}
+
+/// The PFS image of a package, holding the game files. The image is a
+/// compressed (PFSC) file system inside an optionally encrypted outer one
+pub struct Pfs<R> {
+    r: R,
+    image_offset: u64,
+    image_size: u64,
+    xts: Option<Xts>,
+    pfsc_offset: u64,
+    /// Offset of each block from the PFSC header, with the end of the last one
+    block_offsets: Vec<u64>,
+    inodes: Vec<Inode>,
+    superroot_ino: u64,
+}
+
+impl<R: Read + Seek> Pfs<R> {
+    /// `ekpfs` is the image key from the package, only needed when the image is encrypted
+    pub fn open(
+        mut r: R,
+        image_offset: u64,
+        image_size: u64,
+        ekpfs: Option<&[u8; 32]>,
+    ) -> Result<Self, Error> {
+        let file_len = r.seek(SeekFrom::End(0))?;
+        if image_offset.saturating_add(image_size) > file_len || image_size < PFSC_SEARCH_START {
+            return Err(Error::ImageOutOfBounds(image_offset, image_size));
+        }
+
+        r.seek(SeekFrom::Start(image_offset))?;
+        let header = Superblock::read_from_io(&mut r)?;
+        let xts = if header.mode.get() & PFS_MODE_ENCRYPTED != 0 {
+            let ekpfs = ekpfs.ok_or(Error::MissingKey)?;
+            let mut seed = [0u8; 16];
+            r.seek(SeekFrom::Start(image_offset + PFS_SEED_OFFSET))?;
+            r.read_exact(&mut seed)?;
+            Some(Xts::new(ekpfs, &seed))
+        } else {
+            None
+        };
+
+        let mut pfs = Self {
+            r,
+            image_offset,
+            image_size,
+            xts,
+            pfsc_offset: 0,
+            block_offsets: Vec::new(),
+            inodes: Vec::new(),
+            superroot_ino: 0,
+        };
+
+        pfs.pfsc_offset = (PFSC_SEARCH_START..image_size)
+            .step_by(PFSC_SEARCH_STEP as usize)
+            .find_map(|offset| match pfs.read_image(offset, 4) {
+                Ok(magic) if magic == PFSC_MAGIC.to_le_bytes() => Some(Ok(offset)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .ok_or(Error::PfscNotFound)??;
+
+        let pfsc = PfscHeader::read_from_bytes(
+            &pfs.read_image(pfs.pfsc_offset, size_of::<PfscHeader>() as u64)?,
+        )
+        .expect("buffer has the header size");
+        if pfsc.block_sz2.get() != BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize(pfsc.block_sz2.get()));
+        }
+        let block_count = pfsc.data_length.get() / BLOCK_SIZE;
+        let pfsc_size = image_size - pfs.pfsc_offset;
+        let table_size = (block_count + 1) * 8;
+        if pfsc.block_offsets.get().saturating_add(table_size) > pfsc_size {
+            return Err(Error::ImageOutOfBounds(image_offset, image_size));
+        }
+        let table = pfs.read_image(pfs.pfsc_offset + pfsc.block_offsets.get(), table_size)?;
+        pfs.block_offsets = table
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("chunk is 8 bytes")))
+            .collect();
+
+        let superblock = pfs.block(0)?;
+        let superblock = Superblock::read_from_prefix(&superblock)
+            .expect("block is bigger than the superblock")
+            .0;
+        let inode_count = superblock.dinode_count.get();
+        let inode_blocks = inode_count.div_ceil(INODES_PER_BLOCK);
+        if inode_blocks >= block_count {
+            return Err(Error::InodeOutOfBounds(inode_count));
+        }
+        for i in 1..=inode_blocks {
+            let block = pfs.block(i)?;
+            let in_block = (inode_count - pfs.inodes.len() as u64).min(INODES_PER_BLOCK) as usize;
+            for slot in block.chunks_exact(INODE_SIZE).take(in_block) {
+                let inode = Inode::read_from_prefix(slot)
+                    .expect("slot is bigger than an inode")
+                    .0;
+                pfs.inodes.push(inode);
+            }
+        }
+        pfs.superroot_ino = superblock.superroot_ino.get();
+
+        Ok(pfs)
+    }
+
+    /// Reads `len` bytes at `offset` from the start of the image, decrypting the sectors they are in
+    fn read_image(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, Error> {
+        if offset.saturating_add(len) > self.image_size {
+            return Err(Error::ImageOutOfBounds(offset, len));
+        }
+        let Some(xts) = &self.xts else {
+            self.r.seek(SeekFrom::Start(self.image_offset + offset))?;
+            let mut buf = vec![0u8; len as usize];
+            self.r.read_exact(&mut buf)?;
+            return Ok(buf);
+        };
+
+        let first_sector = offset / XTS_SECTOR_SIZE;
+        let end = (offset + len)
+            .next_multiple_of(XTS_SECTOR_SIZE)
+            .min(self.image_size);
+        let start = first_sector * XTS_SECTOR_SIZE;
+        self.r.seek(SeekFrom::Start(self.image_offset + start))?;
+        let mut buf = vec![0u8; (end - start) as usize];
+        self.r.read_exact(&mut buf)?;
+        for (i, sector) in buf.chunks_mut(XTS_SECTOR_SIZE as usize).enumerate() {
+            xts.decrypt_sector(first_sector + i as u64, sector);
+        }
+        let skip = (offset - start) as usize;
+        Ok(buf[skip..skip + len as usize].to_vec())
+    }
+
+    /// Reads a block of the inner image, [`BLOCK_SIZE`] bytes long
+    fn block(&mut self, index: u64) -> Result<Vec<u8>, Error> {
+        let (Some(&start), Some(&end)) = (
+            self.block_offsets.get(index as usize),
+            self.block_offsets.get(index as usize + 1),
+        ) else {
+            return Err(Error::BlockOutOfBounds(index));
+        };
+        if end < start || end - start > BLOCK_SIZE {
+            return Err(Error::BlockOutOfBounds(index));
+        }
+        let data = self.read_image(self.pfsc_offset + start, end - start)?;
+        if data.len() as u64 == BLOCK_SIZE {
+            return Ok(data);
+        }
+
+        let mut block = Vec::with_capacity(BLOCK_SIZE as usize);
+        ZlibDecoder::new(&data[..])
+            .take(BLOCK_SIZE)
+            .read_to_end(&mut block)
+            .map_err(|e| Error::Inflate(index, e))?;
+        block.resize(BLOCK_SIZE as usize, 0);
+        Ok(block)
+    }
+
+    fn inode(&self, ino: u64) -> Result<&Inode, Error> {
+        self.inodes
+            .get(ino as usize)
+            .ok_or(Error::InodeOutOfBounds(ino))
+    }
+
+    /// Entries of a directory, without `.` and `..`
+    fn read_dir(&mut self, ino: u32) -> Result<Vec<(String, u32, PfsFileKind)>, Error> {
+        let inode = self.inode(ino as u64)?;
+        let (loc, blocks) = (inode.loc.get() as u64, inode.blocks.get() as u64);
+
+        let mut entries = Vec::new();
+        for index in loc..loc + blocks {
+            let block = self.block(index)?;
+            let mut pos = 0;
+            while let Ok((dirent, rest)) = DirentHeader::read_from_prefix(&block[pos..]) {
+                if dirent.ino.get() == 0 || dirent.entsize.get() == 0 {
+                    break;
+                }
+                let name = rest
+                    .get(..dirent.namelen.get() as usize)
+                    .ok_or(Error::BlockOutOfBounds(index))?;
+                let name = String::from_utf8_lossy(name).into_owned();
+                let kind = match dirent.kind.get() {
+                    DIRENT_FILE => Some(PfsFileKind::File),
+                    DIRENT_DIR => Some(PfsFileKind::Dir),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    entries.push((name, dirent.ino.get(), kind));
+                }
+                pos = match pos.checked_add(dirent.entsize.get() as usize) {
+                    Some(next) if next < block.len() => next,
+                    _ => break,
+                };
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Lists the game files, each directory before its content
+    pub fn files(&mut self) -> Result<Vec<PfsFile>, Error> {
+        let superroot = u32::try_from(self.superroot_ino)
+            .map_err(|_| Error::InodeOutOfBounds(self.superroot_ino))?;
+        let uroot = self
+            .read_dir(superroot)?
+            .into_iter()
+            .find(|(name, _, kind)| name == "uroot" && *kind == PfsFileKind::Dir)
+            .ok_or(Error::RootNotFound)?;
+
+        let mut files = Vec::new();
+        let mut visited = HashSet::from([uroot.1]);
+        let mut pending = vec![(String::new(), uroot.1)];
+        while let Some((dir, ino)) = pending.pop() {
+            for (name, ino, kind) in self.read_dir(ino)? {
+                // Names are joined to the install folder, they must stay inside it
+                if name.is_empty()
+                    || name == "."
+                    || name == ".."
+                    || name.contains(['/', '\\', ':', '\0'])
+                {
+                    return Err(Error::InvalidName(name));
+                }
+                let path = if dir.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                if kind == PfsFileKind::Dir {
+                    if !visited.insert(ino) {
+                        return Err(Error::DirectoryLoop(ino));
+                    }
+                    pending.push((path.clone(), ino));
+                }
+                files.push(PfsFile {
+                    path,
+                    kind,
+                    size: match kind {
+                        PfsFileKind::File => self.inode(ino as u64)?.size.get(),
+                        PfsFileKind::Dir => 0,
+                    },
+                    ino,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    /// Copies a file to `w` a block at a time, calling `on_block` with the
+    /// amount of bytes written after each one. Returning `false` from it stops the copy
+    pub fn copy_file<W: Write>(
+        &mut self,
+        file: &PfsFile,
+        mut w: W,
+        mut on_block: impl FnMut(usize) -> bool,
+    ) -> Result<bool, Error> {
+        let inode = self.inode(file.ino as u64)?;
+        let (loc, size) = (inode.loc.get() as u64, inode.size.get());
+        if (inode.blocks.get() as u64) < size.div_ceil(BLOCK_SIZE) {
+            return Err(Error::InodeTooSmall(file.ino));
+        }
+
+        let mut remaining = size;
+        let mut index = loc;
+        while remaining > 0 {
+            let block = self.block(index)?;
+            let n = remaining.min(BLOCK_SIZE) as usize;
+            w.write_all(&block[..n])?;
+            remaining -= n as u64;
+            index += 1;
+            if !on_block(n) {
+                return Ok(false);
+            }
+        }
+        w.flush()?;
+        Ok(true)
+    }
+}
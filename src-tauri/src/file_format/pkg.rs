@@ -0,0 +1,597 @@
+use crate::file_format::pfs::{self, Pfs};
+use crate::file_format::pkg::file_format::*;
+use crate::file_format::psf::{self, PSF};
+use aes::Aes128;
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use zerocopy::{FromBytes, IntoBytes};
+
+/// Based on
+/// https://github.com/shadps4-emu/shadPS4/blob/b41664ac616894686e072ede61c609b422d79ed4/src/core/file_format/pkg.h
+mod file_format {
+    use static_assertions::assert_eq_size;
+    use zerocopy::big_endian::{U16, U32, U64};
+    use zerocopy_derive::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+    pub(super) const PKG_MAGIC: u32 = 0x7F434E54;
+
+    pub(super) const PKG_ENTRY_ENCRYPTED_FLAG: u32 = 0x80000000;
+
+    pub(super) const PKG_ENTRY_ID_ENTRY_KEYS: u32 = 0x0010;
+    pub(super) const PKG_ENTRY_ID_IMAGE_KEY: u32 = 0x0020;
+    pub(super) const PKG_ENTRY_ID_ENTRY_NAMES: u32 = 0x0200;
+    pub(super) const PKG_ENTRY_ID_PARAM_SFO: u32 = 0x1000;
+
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct FileHeader {
+        pub magic: U32,
+        pub pkg_type: U32,
+        pub pkg_0x8: U32,
+        pub file_count: U32,
+        pub table_entry_count: U32,
+        pub sc_entry_count: U16,
+        pub table_entry_count_2: U16,
+        pub table_entry_offset: U32,
+        pub sc_entry_data_size: U32,
+        pub body_offset: U64,
+        pub body_size: U64,
+        pub content_offset: U64,
+        pub content_size: U64,
+        pub content_id: [u8; 0x24],
+        pub padding: [u8; 0xC],
+        pub drm_type: U32,
+        pub content_type: U32,
+        pub content_flags: U32,
+        pub promote_size: U32,
+        pub version_date: U32,
+        pub version_hash: U32,
+        pub pkg_0x088: U32,
+        pub pkg_0x08c: U32,
+        pub pkg_0x090: U32,
+        pub pkg_0x094: U32,
+        pub iro_tag: U32,
+        pub drm_type_version: U32,
+        pub zeroes_1: [u8; 0x60],
+        pub digest_entries_1: [u8; 0x20],
+        pub digest_entries_2: [u8; 0x20],
+        pub digest_table_digest: [u8; 0x20],
+        pub digest_body_digest: [u8; 0x20],
+        pub zeroes_2: [u8; 0x280],
+        pub pkg_0x400: U32,
+        pub pfs_image_count: U32,
+        pub pfs_image_flags: U64,
+        pub pfs_image_offset: U64,
+        pub pfs_image_size: U64,
+        pub mount_image_offset: U64,
+        pub mount_image_size: U64,
+        pub pkg_size: U64,
+        pub pfs_signed_size: U32,
+        pub pfs_cache_size: U32,
+        pub pfs_image_digest: [u8; 0x20],
+        pub pfs_signed_digest: [u8; 0x20],
+        pub pfs_split_size_nth_0: U64,
+        pub pfs_split_size_nth_1: U64,
+        pub zeroes_3: [u8; 0xB50],
+        pub pkg_digest: [u8; 0x20],
+    }
+    assert_eq_size!(FileHeader, [u8; 0x1000]);
+
+    #[repr(C)]
+    #[derive(FromBytes, IntoBytes, KnownLayout, Immutable)]
+    pub(super) struct RawEntry {
+        pub id: U32,
+        pub filename_offset: U32,
+        pub flags1: U32,
+        pub flags2: U32,
+        pub offset: U32,
+        pub size: U32,
+        pub padding: U64,
+    }
+    assert_eq_size!(RawEntry, [u8; 0x20]);
+
+    /// The entry keys entry holds a seed digest, 7 digests and 7 keys.
+    /// The 4th key is the one of the PKG derived key 3
+    pub(super) const ENTRY_KEYS_SIZE: usize = 0x20 + 7 * 0x20 + 7 * 0x100;
+    pub(super) const ENTRY_KEYS_DK3: std::ops::Range<usize> = 0x400..0x500;
+    pub(super) const IMAGE_KEY_SIZE: usize = 0x100;
+
+    /// Entries encrypted with their own key, derived from the entry and the PKG derived key 3
+    pub(super) const PKG_ENTRY_ID_NP_FIRST: u32 = 0x0400;
+    pub(super) const PKG_ENTRY_ID_NP_LAST: u32 = 0x0403;
+
+    /// Well-known entries that are stored without a name in the entry names table
+    pub(super) const ENTRY_NAMES: &[(u32, &str)] = &[
+        (0x0001, "digests"),
+        (0x0010, "entry_keys"),
+        (0x0020, "image_key"),
+        (0x0080, "general_digests"),
+        (0x0100, "metas"),
+        (0x0200, "entry_names"),
+        (0x0400, "license.dat"),
+        (0x0401, "license.info"),
+        (0x0402, "nptitle.dat"),
+        (0x0403, "npbind.dat"),
+        (0x0404, "selfinfo.dat"),
+        (0x0406, "imageinfo.dat"),
+        (0x0407, "target-deltainfo.dat"),
+        (0x0408, "origin-deltainfo.dat"),
+        (0x0409, "psreserved.dat"),
+        (0x1000, "param.sfo"),
+        (0x1001, "playgo-chunk.dat"),
+        (0x1002, "playgo-chunk.sha"),
+        (0x1003, "playgo-manifest.xml"),
+        (0x1004, "pronunciation.xml"),
+        (0x1005, "pronunciation.sig"),
+        (0x1006, "pic1.png"),
+        (0x1007, "pubtoolinfo.dat"),
+        (0x100B, "shareparam.json"),
+        (0x100C, "shareoverlayimage.png"),
+        (0x100E, "shareprivacyguardimage.png"),
+        (0x1200, "icon0.png"),
+        (0x1220, "pic0.png"),
+        (0x1240, "snd0.at9"),
+        (0x1260, "changeinfo/changeinfo.xml"),
+        (0x1280, "icon0.dds"),
+        (0x12A0, "pic0.dds"),
+        (0x12C0, "pic1.dds"),
+    ];
+}
+
+/// Entries bigger than this are not read into memory
+const MAX_IN_MEMORY_ENTRY_SIZE: u32 = 64 * 1024 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    #[error("file is too small to be a PKG: {0} bytes")]
+    FileTooSmall(u64),
+    #[error("invalid PKG header magic code: {0:X}")]
+    InvalidMagicCode(u32),
+    #[error("entry table with {0} entries is out of the file")]
+    EntryTableOutOfBounds(u32),
+    #[error("entry {0:X} is out of the file: offset={1:X}, size={2:X}")]
+    EntryOutOfBounds(u32, u32, u32),
+    #[error("entry not found: {0}")]
+    EntryNotFound(String),
+    #[error("entry {0} is encrypted")]
+    EntryEncrypted(String),
+    #[error("entry {0} is too large to be loaded: {1} bytes")]
+    EntryTooLarge(String, u32),
+    #[error("invalid param.sfo: {0}")]
+    InvalidParamSfo(#[from] psf::Error),
+    #[error("invalid {0} key")]
+    InvalidKey(&'static str),
+    #[error("package keys don't match the package: {0}")]
+    KeyMismatch(&'static str),
+    #[error("package has no PFS image")]
+    NoPfsImage,
+    #[error("invalid PFS image: {0}")]
+    InvalidPfs(#[from] pfs::Error),
+}
+
+/// RSA keys that decrypt fake-signed packages. They are not shipped with the launcher
+pub struct PkgKeyset {
+    derived_key3: RsaPrivateKey,
+    fake: RsaPrivateKey,
+}
+
+impl PkgKeyset {
+    /// Both keys are PEM, either PKCS#1 or PKCS#8
+    pub fn from_pem(derived_key3: &str, fake: &str) -> Result<Self, Error> {
+        let parse = |pem: &str, name| {
+            RsaPrivateKey::from_pkcs1_pem(pem)
+                .or_else(|_| RsaPrivateKey::from_pkcs8_pem(pem))
+                .map_err(|_| Error::InvalidKey(name))
+        };
+        Ok(Self {
+            derived_key3: parse(derived_key3, "PKG derived key 3")?,
+            fake: parse(fake, "fake PKG")?,
+        })
+    }
+}
+
+/// Keys of a single package, see [`PKG::keys`]
+pub struct PkgKeys {
+    dk3: [u8; 32],
+    ekpfs: [u8; 32],
+}
+
+fn rsa_decrypt(key: &RsaPrivateKey, data: &[u8], name: &'static str) -> Result<[u8; 32], Error> {
+    let data = key
+        .decrypt(Pkcs1v15Encrypt, data)
+        .map_err(|_| Error::KeyMismatch(name))?;
+    data.get(..32)
+        .and_then(|d| d.try_into().ok())
+        .ok_or(Error::KeyMismatch(name))
+}
+
+/// AES key and IV of an entry, from the start of its table entry and the PKG derived key 3
+fn entry_key(raw: &RawEntry, dk3: &[u8; 32]) -> [u8; 32] {
+    // The padding of the table entry is left out
+    let mut hash = Sha256::new();
+    hash.update(&raw.as_bytes()[..24]);
+    hash.update([0u8; 8]);
+    hash.update(dk3);
+    hash.finalize().into()
+}
+
+fn entry_decrypt(raw: &RawEntry, dk3: &[u8; 32], data: &mut [u8]) {
+    let key = entry_key(raw, dk3);
+    // Only whole AES blocks are encrypted
+    let len = data.len() / 16 * 16;
+    cbc::Decryptor::<Aes128>::new(key[16..].into(), key[..16].into())
+        .decrypt_padded_mut::<NoPadding>(&mut data[..len])
+        .expect("data is a multiple of the block size");
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub id: u32,
+    pub name: Option<String>,
+    pub offset: u32,
+    pub size: u32,
+    pub encrypted: bool,
+}
+
+/// A PS4 package opened for reading. The encrypted np entries and the
+/// encrypted PFS image of fake-signed packages need the [`PkgKeys`]
+pub struct PKG<R> {
+    r: R,
+    header: Box<FileHeader>,
+    entries: Vec<Entry>,
+    /// Same order as `entries`
+    raw_entries: Vec<RawEntry>,
+}
+
+impl PKG<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::read(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> PKG<R> {
+    pub fn read(mut r: R) -> Result<Self, Error> {
+        let file_len = r.seek(SeekFrom::End(0))?;
+        if file_len < size_of::<FileHeader>() as u64 {
+            return Err(Error::FileTooSmall(file_len));
+        }
+
+        r.seek(SeekFrom::Start(0))?;
+        let header = Box::new(FileHeader::read_from_io(&mut r)?);
+        if header.magic != PKG_MAGIC {
+            return Err(Error::InvalidMagicCode(header.magic.get()));
+        }
+
+        let entry_count = header.table_entry_count.get();
+        let table_offset = header.table_entry_offset.get() as u64;
+        if table_offset + entry_count as u64 * size_of::<RawEntry>() as u64 > file_len {
+            return Err(Error::EntryTableOutOfBounds(entry_count));
+        }
+
+        r.seek(SeekFrom::Start(table_offset))?;
+        let mut raw_entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let e = RawEntry::read_from_io(&mut r)?;
+            if e.offset.get() as u64 + e.size.get() as u64 > file_len {
+                return Err(Error::EntryOutOfBounds(
+                    e.id.get(),
+                    e.offset.get(),
+                    e.size.get(),
+                ));
+            }
+            raw_entries.push(e);
+        }
+
+        let mut entries: Vec<Entry> = raw_entries
+            .iter()
+            .map(|e| Entry {
+                id: e.id.get(),
+                name: ENTRY_NAMES
+                    .iter()
+                    .find(|(id, _)| *id == e.id.get())
+                    .map(|(_, name)| name.to_string()),
+                offset: e.offset.get(),
+                size: e.size.get(),
+                encrypted: e.flags1.get() & PKG_ENTRY_ENCRYPTED_FLAG != 0,
+            })
+            .collect();
+
+        let mut pkg = Self {
+            r,
+            header,
+            entries: Vec::new(),
+            raw_entries: Vec::new(),
+        };
+
+        // Entries missing from the well-known list may have a name in the entry names table
+        let names_entry = entries
+            .iter()
+            .find(|e| e.id == PKG_ENTRY_ID_ENTRY_NAMES && !e.encrypted)
+            .cloned();
+        if let Some(names_entry) = names_entry
+            && names_entry.size <= MAX_IN_MEMORY_ENTRY_SIZE
+        {
+            let names = pkg.read_raw_entry(&names_entry)?;
+            for (e, raw) in entries.iter_mut().zip(&raw_entries) {
+                let name_offset = raw.filename_offset.get() as usize;
+                if e.name.is_some() || name_offset == 0 || name_offset >= names.len() {
+                    continue;
+                }
+                let name = &names[name_offset..];
+                let end = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                e.name = String::from_utf8(name[..end].to_vec()).ok();
+            }
+        }
+        pkg.entries = entries;
+        pkg.raw_entries = raw_entries;
+
+        Ok(pkg)
+    }
+
+    pub fn content_id(&self) -> String {
+        let id = &self.header.content_id;
+        let end = id.iter().position(|c| *c == 0).unwrap_or(id.len());
+        String::from_utf8_lossy(&id[..end]).into_owned()
+    }
+
+    /// The title id (CUSA) embedded in the content id. E.g. UP0000-CUSA00000_00-... => CUSA00000
+    pub fn title_id(&self) -> Option<String> {
+        self.content_id().get(7..16).map(str::to_owned)
+    }
+
+    pub fn content_type(&self) -> u32 {
+        self.header.content_type.get()
+    }
+
+    pub fn pkg_size(&self) -> u64 {
+        self.header.pkg_size.get()
+    }
+
+    pub fn pfs_image_offset(&self) -> u64 {
+        self.header.pfs_image_offset.get()
+    }
+
+    pub fn pfs_image_size(&self) -> u64 {
+        self.header.pfs_image_size.get()
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn find_entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name.as_deref() == Some(name))
+    }
+
+    fn read_raw_entry(&mut self, entry: &Entry) -> Result<Vec<u8>, Error> {
+        self.r.seek(SeekFrom::Start(entry.offset as u64))?;
+        let mut buf = vec![0u8; entry.size as usize];
+        self.r.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Reads an unencrypted entry by name. E.g. `icon0.png`
+    pub fn read_entry(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let Some(entry) = self.find_entry(name).cloned() else {
+            return Err(Error::EntryNotFound(name.to_owned()));
+        };
+        if entry.encrypted {
+            return Err(Error::EntryEncrypted(name.to_owned()));
+        }
+        if entry.size > MAX_IN_MEMORY_ENTRY_SIZE {
+            return Err(Error::EntryTooLarge(name.to_owned(), entry.size));
+        }
+        self.read_raw_entry(&entry)
+    }
+
+    /// Copies an unencrypted entry to `w` in chunks, calling `on_chunk` with the
+    /// amount of bytes written after each one. Returning `false` from it stops the copy
+    pub fn copy_entry<W: io::Write>(
+        &mut self,
+        entry: &Entry,
+        mut w: W,
+        mut on_chunk: impl FnMut(usize) -> bool,
+    ) -> Result<bool, Error> {
+        let name = entry.name.clone().unwrap_or_else(|| format!("{:X}", entry.id));
+        if entry.encrypted {
+            return Err(Error::EntryEncrypted(name));
+        }
+        self.r.seek(SeekFrom::Start(entry.offset as u64))?;
+        let mut r = (&mut self.r).take(entry.size as u64);
+        let mut buf = vec![0u8; 1024 * 1024];
+        loop {
+            let n = r.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            w.write_all(&buf[..n])?;
+            if !on_chunk(n) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn raw_entry_by_id(&self, id: u32) -> Result<(Entry, &RawEntry), Error> {
+        self.entries
+            .iter()
+            .zip(&self.raw_entries)
+            .find(|(e, _)| e.id == id)
+            .map(|(e, raw)| (e.clone(), raw))
+            .ok_or_else(|| {
+                let name = ENTRY_NAMES.iter().find(|(i, _)| *i == id).map(|(_, n)| *n);
+                Error::EntryNotFound(name.unwrap_or_default().to_owned())
+            })
+    }
+
+    /// Decrypts the key of the PFS image, and the one of the encrypted np entries
+    pub fn keys(&mut self, keyset: &PkgKeyset) -> Result<PkgKeys, Error> {
+        let (entry, _) = self.raw_entry_by_id(PKG_ENTRY_ID_ENTRY_KEYS)?;
+        let entry_keys = self.read_raw_entry(&entry)?;
+        if entry_keys.len() < ENTRY_KEYS_SIZE {
+            return Err(Error::KeyMismatch("entry keys are too small"));
+        }
+        let dk3 = rsa_decrypt(
+            &keyset.derived_key3,
+            &entry_keys[ENTRY_KEYS_DK3],
+            "could not decrypt the derived key 3",
+        )?;
+
+        let (entry, _) = self.raw_entry_by_id(PKG_ENTRY_ID_IMAGE_KEY)?;
+        let mut image_key = self.read_raw_entry(&entry)?;
+        if image_key.len() < IMAGE_KEY_SIZE {
+            return Err(Error::KeyMismatch("image key is too small"));
+        }
+        image_key.truncate(IMAGE_KEY_SIZE);
+        let (_, raw) = self.raw_entry_by_id(PKG_ENTRY_ID_IMAGE_KEY)?;
+        entry_decrypt(raw, &dk3, &mut image_key);
+        let ekpfs = rsa_decrypt(&keyset.fake, &image_key, "could not decrypt the image key")?;
+
+        Ok(PkgKeys { dk3, ekpfs })
+    }
+
+    /// Reads an entry, decrypting it when it's one of the encrypted np entries (e.g. `npbind.dat`)
+    pub fn decrypt_entry(&mut self, name: &str, keys: &PkgKeys) -> Result<Vec<u8>, Error> {
+        let Some(entry) = self.find_entry(name).cloned() else {
+            return Err(Error::EntryNotFound(name.to_owned()));
+        };
+        if !entry.encrypted {
+            return self.read_entry(name);
+        }
+        if !(PKG_ENTRY_ID_NP_FIRST..=PKG_ENTRY_ID_NP_LAST).contains(&entry.id) {
+            return Err(Error::EntryEncrypted(name.to_owned()));
+        }
+        if entry.size > MAX_IN_MEMORY_ENTRY_SIZE {
+            return Err(Error::EntryTooLarge(name.to_owned(), entry.size));
+        }
+        let mut data = self.read_raw_entry(&entry)?;
+        let (_, raw) = self.raw_entry_by_id(entry.id)?;
+        entry_decrypt(raw, &keys.dk3, &mut data);
+        Ok(data)
+    }
+
+    /// Opens the PFS image holding the game files. `keys` are only needed when the image is encrypted
+    pub fn pfs(&mut self, keys: Option<&PkgKeys>) -> Result<Pfs<&mut R>, Error> {
+        let (offset, size) = (self.pfs_image_offset(), self.pfs_image_size());
+        if size == 0 {
+            return Err(Error::NoPfsImage);
+        }
+        Ok(Pfs::open(
+            &mut self.r,
+            offset,
+            size,
+            keys.map(|k| &k.ekpfs),
+        )?)
+    }
+
+    pub fn param_sfo(&mut self) -> Result<PSF, Error> {
+        let Some(entry) = self
+            .entries
+            .iter()
+            .find(|e| e.id == PKG_ENTRY_ID_PARAM_SFO)
+            .cloned()
+        else {
+            return Err(Error::EntryNotFound("param.sfo".to_owned()));
+        };
+        let name = entry.name.clone().unwrap_or_default();
+        let data = self.read_entry(&name)?;
+        Ok(PSF::from_bytes(&data)?)
+    }
+}
+
+pub mod js {
+    use crate::file_format::pkg::{Entry, PKG};
+    use crate::file_format::psf::js::ParamSfoInfo;
+    use anyhow_tauri::IntoTAResult;
+    use log::error;
+    use serde::Serialize;
+    use std::fs::File;
+    use tauri::AppHandle;
+    use tauri::ipc::Response;
+    use tauri_plugin_fs::{FsExt, OpenOptions, SafeFilePath};
+
+    /// Entries that can be read from the frontend before the package is installed
+    const READABLE_ENTRIES: &[&str] = &["param.sfo", "icon0.png", "pic0.png", "pic1.png"];
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PkgInfo {
+        content_id: String,
+        title_id: Option<String>,
+        content_type: u32,
+        pkg_size: u64,
+        param_sfo: Option<ParamSfoInfo>,
+        entries: Vec<Entry>,
+    }
+
+    fn open_pkg(app: &AppHandle, path: SafeFilePath) -> anyhow::Result<PKG<File>> {
+        let path = path.into_path()?;
+        let f = app
+            .fs()
+            .open(&path, OpenOptions::new().read(true).clone())?;
+        Ok(PKG::read(f).inspect_err(|e| error!("error reading pkg file: {}", e))?)
+    }
+
+    #[tauri::command]
+    pub async fn read_pkg_info(
+        app: AppHandle,
+        path: SafeFilePath,
+    ) -> anyhow_tauri::TAResult<PkgInfo> {
+        tokio::task::spawn_blocking(move || _read_pkg_info(&app, path))
+            .await
+            .into_ta_result()?
+            .into_ta_result()
+    }
+
+    fn _read_pkg_info(app: &AppHandle, path: SafeFilePath) -> anyhow::Result<PkgInfo> {
+        let mut pkg = open_pkg(app, path)?;
+
+        let param_sfo = pkg
+            .param_sfo()
+            .inspect_err(|e| error!("could not read param.sfo from pkg: {}", e))
+            .ok()
+            .map(ParamSfoInfo::from);
+
+        Ok(PkgInfo {
+            content_id: pkg.content_id(),
+            title_id: pkg.title_id(),
+            content_type: pkg.content_type(),
+            pkg_size: pkg.pkg_size(),
+            param_sfo,
+            entries: pkg.entries().to_vec(),
+        })
+    }
+
+    #[tauri::command]
+    pub async fn read_pkg_entry(
+        app: AppHandle,
+        path: SafeFilePath,
+        name: String,
+    ) -> anyhow_tauri::TAResult<Response> {
+        if !READABLE_ENTRIES.contains(&name.as_str()) {
+            return Err(anyhow::anyhow!("entry can't be read: {}", name)).into_ta_result();
+        }
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Response> {
+            let mut pkg = open_pkg(&app, path)?;
+            let data = pkg
+                .read_entry(&name)
+                .inspect_err(|e| error!("could not read pkg entry {}: {}", name, e))?;
+            Ok(Response::new(data))
+        })
+        .await
+        .into_ta_result()?
+        .into_ta_result()
+    }
+}
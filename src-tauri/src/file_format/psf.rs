@@ -4,27 +4,32 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{metadata, File};
 use std::io;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::string::FromUtf8Error;
 use std::time::SystemTime;
-use zerocopy::FromBytes;
+use zerocopy::{FromBytes, IntoBytes};
 
-/// This is a direct conversion from
-/// https://github.com/shadps4-emu/shadPS4/blob/b41664ac616894686e072ede61c609b422d79ed4/src/core/file_format/psf.h
+pub mod param_sfo;
+
+// This is a direct conversion from
+// https://github.com/shadps4-emu/shadPS4/blob/b41664ac616894686e072ede61c609b422d79ed4/src/core/file_format/psf.h
 
 mod file_format {
     use num_derive::FromPrimitive;
     use static_assertions::assert_eq_size;
     use zerocopy::big_endian;
-    use zerocopy_derive::{FromBytes, Immutable, KnownLayout};
+    use zerocopy_derive::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
     pub(super) const PSF_MAGIC: u32 = 0x00505346;
     pub(super) const PSF_VERSION_1_1: u32 = 0x00000101;
     pub(super) const PSF_VERSION_1_0: u32 = 0x00000100;
 
+    /// The key table and newly created text entries are padded to this alignment
+    pub(super) const PSF_TABLE_ALIGN: usize = 4;
+
     #[repr(C)]
-    #[derive(FromBytes, KnownLayout, Immutable)]
+    #[derive(FromBytes, IntoBytes, KnownLayout, Immutable)]
     pub(super) struct FileHeader {
         pub magic: big_endian::U32,
         pub version: u32,
@@ -35,7 +40,7 @@ mod file_format {
     assert_eq_size!(FileHeader, [u8; 0x14]);
 
     #[repr(C)]
-    #[derive(FromBytes, KnownLayout, Immutable)]
+    #[derive(FromBytes, IntoBytes, KnownLayout, Immutable)]
     pub(super) struct RawEntry {
         pub key_offset: u16,
         pub param_fmt: big_endian::U16,
@@ -46,7 +51,7 @@ mod file_format {
     assert_eq_size!(RawEntry, [u8; 0x10]);
 
     #[repr(u16)]
-    #[derive(FromPrimitive)]
+    #[derive(FromPrimitive, Clone, Copy)]
     pub(super) enum EntryFmt {
         Binary = 0x0400,  // Binary data
         Text = 0x0402,    // String in UTF-8 format and NULL terminated
@@ -54,13 +59,44 @@ mod file_format {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     Binary(Vec<u8>),
     Text(String),
     Integer(i32),
 }
 
+impl Value {
+    fn fmt(&self) -> EntryFmt {
+        match self {
+            Value::Binary(_) => EntryFmt::Binary,
+            Value::Text(_) => EntryFmt::Text,
+            Value::Integer(_) => EntryFmt::Integer,
+        }
+    }
+
+    /// Size of the value in the data table, without padding
+    fn param_len(&self) -> usize {
+        match self {
+            Value::Binary(data) => data.len(),
+            Value::Text(text) => text.len() + 1, // NULL terminator
+            Value::Integer(_) => size_of::<i32>(),
+        }
+    }
+
+    /// Writes the [`Value::param_len`] bytes of the value
+    fn write_data<W: Write>(&self, mut w: W) -> io::Result<()> {
+        match self {
+            Value::Binary(data) => w.write_all(data),
+            Value::Text(text) => {
+                w.write_all(text.as_bytes())?;
+                w.write_all(&[0])
+            }
+            Value::Integer(i) => w.write_all(&i.to_le_bytes()),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("io error: {0}")]
@@ -75,12 +111,57 @@ pub enum Error {
     InvalidTextEntryUtf(#[from] FromUtf8Error),
     #[error("invalid integer entry size: {0} != {1}")]
     InvalidIntEntry(u32, usize),
+    #[error("file is too small to be a PSF: {0} bytes")]
+    FileTooSmall(u64),
+    #[error("index table with {0} entries doesn't fit before the key table")]
+    IndexTableOutOfBounds(u32),
+    #[error("invalid table offsets: key_table={0:X}, data_table={1:X}, file_len={2:X}")]
+    InvalidTableOffsets(u32, u32, u64),
+    #[error("key offset out of the key table: {0:X}")]
+    KeyOutOfBounds(u16),
+    #[error("key at offset {0:X} is not NULL terminated")]
+    UnterminatedKey(u16),
+    #[error("entry {0} is longer than its max length: {1} > {2}")]
+    InvalidEntryLength(String, u32, u32),
+    #[error("entry {0} data is out of the file: offset={1:X}, len={2:X}")]
+    DataOutOfBounds(String, u64, u32),
+    #[error("duplicated entry key: {0}")]
+    DuplicateKey(String),
+    #[error("text entry contains a NULL byte: {0}")]
+    TextEntryWithNull(String),
+    #[error("key table is too large: {0} bytes")]
+    KeyTableTooLarge(usize),
+    #[error("data table is too large: {0} bytes")]
+    DataTableTooLarge(usize),
+}
+
+/// Where an entry was placed in the file, so writing it back keeps the same layout
+#[derive(Debug, Clone)]
+struct EntryLayout {
+    key: String,
+    param_len: u32,
+    param_max_len: u32,
+    /// Offset of the reserved `param_max_len` bytes in the file
+    data_start: usize,
+    /// The value as read, an entry equal to it is written back with its original bytes
+    value: Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PSF {
     last_write: SystemTime,
     entries: HashMap<String, Value>,
+    #[serde(skip, default = "default_version")]
+    version: u32,
+    #[serde(skip)]
+    layout: Vec<EntryLayout>,
+    /// The file as read, patched in place when the changes fit in it
+    #[serde(skip)]
+    original: Vec<u8>,
+}
+
+fn default_version() -> u32 {
+    PSF_VERSION_1_1
 }
 
 impl Default for PSF {
@@ -88,10 +169,17 @@ impl Default for PSF {
         Self {
             last_write: SystemTime::UNIX_EPOCH,
             entries: HashMap::new(),
+            version: default_version(),
+            layout: Vec::new(),
+            original: Vec::new(),
         }
     }
 }
 
+fn align_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
 impl PSF {
     pub fn new() -> Self {
         Default::default()
@@ -101,14 +189,31 @@ impl PSF {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let metadata = metadata(&path)?;
         let f = File::open(&path)?;
-        let mut s = Self::default();
-        s.last_write = metadata.modified()?;
+        let mut s = Self {
+            last_write: metadata.modified()?,
+            ..Default::default()
+        };
         s.read(f)?;
         Ok(s)
     }
 
+    /// Parses a PSF already loaded in memory
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut s = Self::default();
+        s.read(Cursor::new(data))?;
+        Ok(s)
+    }
+
     fn read<R: Read + Seek>(&mut self, mut r: R) -> Result<(), Error> {
+        let file_len = r.seek(SeekFrom::End(0))?;
+        if file_len < size_of::<FileHeader>() as u64 {
+            return Err(Error::FileTooSmall(file_len));
+        }
+
         r.seek(SeekFrom::Start(0))?;
+        let mut original = Vec::with_capacity(file_len as usize);
+        r.read_to_end(&mut original)?;
+        let mut r = Cursor::new(original.as_slice());
         let header = FileHeader::read_from_io(&mut r)?;
         if header.magic != PSF_MAGIC {
             return Err(Error::InvalidMagicCode(header.magic.get()));
@@ -117,78 +222,390 @@ impl PSF {
             return Err(Error::InvalidVersion(header.version));
         }
 
+        let key_table_offset = header.key_table_offset as u64;
+        let data_table_offset = header.data_table_offset as u64;
+        let index_table_end = size_of::<FileHeader>() as u64
+            + header.index_table_entries as u64 * size_of::<RawEntry>() as u64;
+        if index_table_end > key_table_offset.min(file_len) {
+            return Err(Error::IndexTableOutOfBounds(header.index_table_entries));
+        }
+        if key_table_offset > data_table_offset || data_table_offset > file_len {
+            return Err(Error::InvalidTableOffsets(
+                header.key_table_offset,
+                header.data_table_offset,
+                file_len,
+            ));
+        }
+
         let mut entries = Vec::<RawEntry>::new();
         entries.reserve_exact(header.index_table_entries as usize);
         for _ in 0..header.index_table_entries {
             entries.push(RawEntry::read_from_io(&mut r)?);
         }
 
-        self.entries = entries
-            .iter()
-            .map(|e| -> Result<(String, Value), Error> {
-                r.seek(SeekFrom::Start(
-                    (header.key_table_offset + e.key_offset as u32) as u64,
-                ))?;
+        let mut layout = Vec::with_capacity(entries.len());
+        let mut values = HashMap::with_capacity(entries.len());
+        for e in &entries {
+            let key_start = key_table_offset + e.key_offset as u64;
+            if key_start >= data_table_offset {
+                return Err(Error::KeyOutOfBounds(e.key_offset));
+            }
+            r.seek(SeekFrom::Start(key_start))?;
+
+            let key = {
+                let mut data = Vec::new();
+                let mut r = BufReader::new((&mut r).take(data_table_offset - key_start));
+                r.read_until(b'\0', &mut data)?;
+                if data.last() != Some(&0) {
+                    return Err(Error::UnterminatedKey(e.key_offset));
+                }
+                data.pop();
+                String::from_utf8(data)?
+            };
+
+            if e.param_len > e.param_max_len {
+                return Err(Error::InvalidEntryLength(key, e.param_len, e.param_max_len));
+            }
+            let data_start = data_table_offset + e.data_offset as u64;
+            if data_start + e.param_max_len as u64 > file_len {
+                return Err(Error::DataOutOfBounds(key, data_start, e.param_max_len));
+            }
+            r.seek(SeekFrom::Start(data_start))?;
 
-                let key = {
+            let Some(fmt) = EntryFmt::from_u16(e.param_fmt.get()) else {
+                return Err(Error::InvalidEntryFormat(e.param_fmt.get()));
+            };
+
+            let value: Value = match fmt {
+                EntryFmt::Binary => {
+                    let mut buf = vec![0u8; e.param_len as usize];
+                    r.read_exact(&mut buf)?;
+                    Value::Binary(buf)
+                }
+                EntryFmt::Text => {
                     let mut data = Vec::new();
-                    let mut r = BufReader::new(&mut r);
+                    let mut r = BufReader::new((&mut r).take(e.param_max_len as u64));
                     r.read_until(b'\0', &mut data)?;
                     if data.last() == Some(&0) {
                         data.pop();
                     }
-                    String::from_utf8(data)?
-                };
+                    Value::Text(String::from_utf8(data)?)
+                }
+                EntryFmt::Integer => {
+                    if e.param_len as usize != size_of::<i32>() {
+                        return Err(Error::InvalidIntEntry(e.param_len, size_of::<i32>()));
+                    }
+                    let mut data = [0; size_of::<i32>()];
+                    r.read_exact(&mut data)?;
+                    Value::Integer(i32::from_le_bytes(data))
+                }
+            };
 
-                r.seek(SeekFrom::Start(
-                    (header.data_table_offset + e.data_offset) as u64,
-                ))?;
+            if values.contains_key(&key) {
+                return Err(Error::DuplicateKey(key));
+            }
+            layout.push(EntryLayout {
+                key: key.clone(),
+                param_len: e.param_len,
+                param_max_len: e.param_max_len,
+                data_start: data_start as usize,
+                value: value.clone(),
+            });
+            values.insert(key, value);
+        }
+        self.entries = values;
+        self.version = header.version;
+        self.layout = layout;
+        self.original = original;
 
-                let Some(fmt) = EntryFmt::from_u16(e.param_fmt.get()) else {
-                    return Err(Error::InvalidEntryFormat(e.param_fmt.get()));
-                };
+        Ok(())
+    }
 
-                let value: Value = match fmt {
-                    EntryFmt::Binary => {
-                        let mut buf = vec![0u8; e.param_len as usize];
-                        r.read_exact(&mut buf)?;
-                        Value::Binary(buf)
-                    }
-                    EntryFmt::Text => {
-                        let mut data = Vec::new();
-                        let mut r = BufReader::new(&mut r);
-                        r.read_until(b'\0', &mut data)?;
-                        if data.last() == Some(&0) {
-                            data.pop();
-                        }
-                        Value::Text(String::from_utf8(data)?)
-                    }
-                    EntryFmt::Integer => {
-                        if e.param_len as usize != size_of::<i32>() {
-                            return Err(Error::InvalidIntEntry(e.param_len, size_of::<i32>()));
-                        }
-                        let mut data = [0; size_of::<i32>()];
-                        r.read_exact(&mut data)?;
-                        Value::Integer(i32::from_le_bytes(data))
-                    }
-                };
+    pub fn last_write(&self) -> SystemTime {
+        self.last_write
+    }
 
-                Ok((key, value))
+    /// For PSFs read from memory, e.g. from a package
+    pub fn set_last_write(&mut self, last_write: SystemTime) {
+        self.last_write = last_write;
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    /// Inserts or replaces an entry. Existing entries keep their position and
+    /// reserved size unless the new value doesn't fit anymore
+    #[allow(dead_code)]
+    pub fn set(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.entries.insert(key.into(), value)
+    }
+
+    #[allow(dead_code)]
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.entries.remove(key)
+    }
+
+    #[allow(dead_code)]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut f = BufWriter::new(File::create(path)?);
+        self.write(&mut f)?;
+        f.flush()?;
+        Ok(())
+    }
+
+    /// Entries in the order they will be written.
+    /// Entries read from a file keep their original order, new ones are appended sorted by key
+    fn write_layout(&self) -> Vec<WriteEntry<'_>> {
+        let mut list: Vec<WriteEntry> = self
+            .layout
+            .iter()
+            .filter_map(|l| {
+                let value = self.entries.get(&l.key)?;
+                if *value == l.value {
+                    return Some(WriteEntry {
+                        key: &l.key,
+                        value,
+                        param_len: l.param_len,
+                        param_max_len: l.param_max_len,
+                        original: Some(l.original_data(&self.original)),
+                    });
+                }
+                let param_len = value.param_len() as u32;
+                Some(WriteEntry {
+                    key: &l.key,
+                    value,
+                    param_len,
+                    param_max_len: l.param_max_len.max(param_len),
+                    original: None,
+                })
             })
-            .collect::<Result<HashMap<String, Value>, Error>>()?;
+            .collect();
+
+        let mut new_entries: Vec<WriteEntry> = self
+            .entries
+            .iter()
+            .filter(|(k, _)| !self.layout.iter().any(|l| &l.key == *k))
+            .map(|(k, v)| {
+                let max_len = match v {
+                    Value::Text(_) => align_up(v.param_len(), PSF_TABLE_ALIGN),
+                    _ => v.param_len(),
+                };
+                WriteEntry {
+                    key: k,
+                    value: v,
+                    param_len: v.param_len() as u32,
+                    param_max_len: max_len as u32,
+                    original: None,
+                }
+            })
+            .collect();
+        new_entries.sort_by_key(|e| e.key);
+        list.extend(new_entries);
+
+        list
+    }
+
+    /// The file as read with the changed values written over their old ones.
+    /// `None` when entries were added or removed, or a new value is larger than its reserved size
+    fn write_in_place(&self) -> Result<Option<Vec<u8>>, Error> {
+        if self.original.is_empty()
+            || self.entries.len() != self.layout.len()
+            || self
+                .layout
+                .iter()
+                .any(|l| !self.entries.contains_key(&l.key))
+        {
+            return Ok(None);
+        }
+
+        let mut out = self.original.clone();
+        for (i, l) in self.layout.iter().enumerate() {
+            let value = &self.entries[&l.key];
+            if *value == l.value {
+                continue;
+            }
+            check_value(&l.key, value)?;
+            let param_len = value.param_len();
+            if param_len > l.param_max_len as usize {
+                return Ok(None);
+            }
+
+            let index_start = size_of::<FileHeader>() + i * size_of::<RawEntry>();
+            let raw = RawEntry::mut_from_bytes(&mut out[index_start..][..size_of::<RawEntry>()])
+                .expect("index entries were read from these bytes");
+            raw.param_fmt = (value.fmt() as u16).into();
+            raw.param_len = param_len as u32;
+
+            let mut slot = &mut out[l.data_start..][..l.param_max_len as usize];
+            value.write_data(&mut slot)?;
+            slot.fill(0);
+        }
+        Ok(Some(out))
+    }
+
+    /// Serializes the PSF back to the param.sfo binary format.
+    /// Unchanged entries keep their original bytes, and their offsets too when the changed
+    /// values fit in the space reserved for them. A PSF written without changes produces the
+    /// same bytes it was read from
+    pub fn write<W: Write>(&self, mut w: W) -> Result<(), Error> {
+        if let Some(data) = self.write_in_place()? {
+            w.write_all(&data)?;
+            return Ok(());
+        }
+
+        let entries = self.write_layout();
+
+        let key_table_offset = size_of::<FileHeader>() + entries.len() * size_of::<RawEntry>();
+        let key_table_size: usize = entries.iter().map(|e| e.key.len() + 1).sum();
+        if key_table_size > u16::MAX as usize {
+            return Err(Error::KeyTableTooLarge(key_table_size));
+        }
+        let data_table_offset = align_up(key_table_offset + key_table_size, PSF_TABLE_ALIGN);
+        let data_table_size: usize = entries.iter().map(|e| e.param_max_len as usize).sum();
+        if data_table_offset + data_table_size > u32::MAX as usize {
+            return Err(Error::DataTableTooLarge(data_table_size));
+        }
+
+        let header = FileHeader {
+            magic: PSF_MAGIC.into(),
+            version: self.version,
+            key_table_offset: key_table_offset as u32,
+            data_table_offset: data_table_offset as u32,
+            index_table_entries: entries.len() as u32,
+        };
+        w.write_all(header.as_bytes())?;
+
+        let mut key_offset = 0usize;
+        let mut data_offset = 0usize;
+        for e in &entries {
+            if e.original.is_none() {
+                check_value(e.key, e.value)?;
+            }
+            let raw = RawEntry {
+                key_offset: key_offset as u16,
+                param_fmt: (e.value.fmt() as u16).into(),
+                param_len: e.param_len,
+                param_max_len: e.param_max_len,
+                data_offset: data_offset as u32,
+            };
+            w.write_all(raw.as_bytes())?;
+            key_offset += e.key.len() + 1;
+            data_offset += e.param_max_len as usize;
+        }
+
+        for e in &entries {
+            w.write_all(e.key.as_bytes())?;
+            w.write_all(&[0])?;
+        }
+        w.write_all(&vec![0u8; data_table_offset - key_table_offset - key_table_size])?;
+
+        for e in &entries {
+            match e.original {
+                Some(data) => w.write_all(data)?,
+                None => {
+                    e.value.write_data(&mut w)?;
+                    w.write_all(&vec![0u8; (e.param_max_len - e.param_len) as usize])?;
+                }
+            }
+        }
 
         Ok(())
     }
 }
 
+/// An entry as [`PSF::write`] lays it out when the file is rebuilt
+struct WriteEntry<'a> {
+    key: &'a str,
+    value: &'a Value,
+    param_len: u32,
+    param_max_len: u32,
+    /// The reserved bytes as read, for unchanged entries
+    original: Option<&'a [u8]>,
+}
+
+impl EntryLayout {
+    fn original_data<'a>(&self, original: &'a [u8]) -> &'a [u8] {
+        &original[self.data_start..][..self.param_max_len as usize]
+    }
+}
+
+fn check_value(key: &str, value: &Value) -> Result<(), Error> {
+    if let Value::Text(text) = value
+        && text.contains('\0')
+    {
+        return Err(Error::TextEntryWithNull(key.to_string()));
+    }
+    Ok(())
+}
+
 pub mod js {
     use crate::file_format::psf::PSF;
+    use crate::file_format::psf::param_sfo::{
+        Attributes, Category, FirmwareVersion, Language, ParamSfo,
+    };
     use anyhow_tauri::IntoTAResult;
     use log::error;
+    use serde::Serialize;
     use std::fs::metadata;
     use tauri::AppHandle;
     use tauri_plugin_fs::{FsExt, OpenOptions, SafeFilePath};
 
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LocalizedTitle {
+        language: Language,
+        title: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ParamSfoInfo {
+        title_id: Option<String>,
+        title: Option<String>,
+        localized_titles: Vec<LocalizedTitle>,
+        app_ver: Option<String>,
+        version: Option<String>,
+        category: Option<Category>,
+        content_id: Option<String>,
+        system_ver: Option<FirmwareVersion>,
+        attribute: Option<Attributes>,
+        parental_level: Option<u32>,
+        supported_languages: Vec<Language>,
+        psf: PSF,
+    }
+
+    impl From<PSF> for ParamSfoInfo {
+        fn from(psf: PSF) -> Self {
+            let sfo = ParamSfo::new(&psf);
+            let supported_languages = sfo.supported_languages();
+            let localized_titles = supported_languages
+                .iter()
+                .filter_map(|l| {
+                    Some(LocalizedTitle {
+                        language: *l,
+                        title: sfo.localized_title(*l)?.to_owned(),
+                    })
+                })
+                .collect();
+            Self {
+                title_id: sfo.title_id().map(str::to_owned),
+                title: sfo.title().map(str::to_owned),
+                localized_titles,
+                app_ver: sfo.app_ver().map(str::to_owned),
+                version: sfo.version().map(str::to_owned),
+                category: sfo.category(),
+                content_id: sfo.content_id().map(str::to_owned),
+                system_ver: sfo.system_ver(),
+                attribute: sfo.attribute(),
+                parental_level: sfo.parental_level(),
+                supported_languages,
+                psf,
+            }
+        }
+    }
+
     #[tauri::command]
     pub async fn read_psf(app: AppHandle, path: SafeFilePath) -> anyhow_tauri::TAResult<PSF> {
         tokio::task::spawn_blocking(|| _read_psf(app, path))
@@ -197,6 +614,18 @@ pub mod js {
             .into_ta_result()
     }
 
+    #[tauri::command]
+    pub async fn read_param_sfo(
+        app: AppHandle,
+        path: SafeFilePath,
+    ) -> anyhow_tauri::TAResult<ParamSfoInfo> {
+        let psf = tokio::task::spawn_blocking(|| _read_psf(app, path))
+            .await
+            .into_ta_result()?
+            .into_ta_result()?;
+        Ok(psf.into())
+    }
+
     fn _read_psf(app: AppHandle, path: SafeFilePath) -> anyhow::Result<PSF> {
         let path = path.into_path()?;
         let mut f = app
@@ -0,0 +1,216 @@
+use crate::file_format::psf::{PSF, Value};
+use bitflags::bitflags;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// Typed view over the well-known keys of a game param.sfo
+pub struct ParamSfo<'a> {
+    psf: &'a PSF,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, strum::EnumIter)]
+#[serde(rename_all = "camelCase")]
+#[repr(u8)]
+pub enum Language {
+    Japanese = 0,
+    EnglishUs = 1,
+    French = 2,
+    Spanish = 3,
+    German = 4,
+    Italian = 5,
+    Dutch = 6,
+    PortuguesePt = 7,
+    Russian = 8,
+    Korean = 9,
+    ChineseTraditional = 10,
+    ChineseSimplified = 11,
+    Finnish = 12,
+    Swedish = 13,
+    Danish = 14,
+    Norwegian = 15,
+    Polish = 16,
+    PortugueseBr = 17,
+    EnglishUk = 18,
+    Turkish = 19,
+    SpanishLa = 20,
+    Arabic = 21,
+    FrenchCa = 22,
+    Czech = 23,
+    Hungarian = 24,
+    Greek = 25,
+    Romanian = 26,
+    Thai = 27,
+    Vietnamese = 28,
+    Indonesian = 29,
+}
+
+impl Language {
+    /// Key holding the title in this language. E.g. TITLE_01
+    pub fn title_key(self) -> String {
+        format!("TITLE_{:02}", self as u8)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+    /// Digital application (gd)
+    Application,
+    /// Application patch (gp)
+    Patch,
+    /// Additional content (ac)
+    AdditionalContent,
+    /// Save data (sd)
+    SaveData,
+    Other(String),
+}
+
+impl From<&str> for Category {
+    fn from(value: &str) -> Self {
+        match value {
+            "gd" => Category::Application,
+            "gp" => Category::Patch,
+            "ac" => Category::AdditionalContent,
+            "sd" => Category::SaveData,
+            other => Category::Other(other.to_owned()),
+        }
+    }
+}
+
+/// Minimum firmware version required by the application, decoded from SYSTEM_VER
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl FirmwareVersion {
+    /// SYSTEM_VER stores the version as BCD in the upper bytes. E.g. 0x05050000 => 5.05
+    pub fn from_system_ver(value: u32) -> Self {
+        let bcd = |b: u8| (b >> 4) * 10 + (b & 0xF);
+        let [major, minor, ..] = value.to_be_bytes();
+        Self {
+            major: bcd(major),
+            minor: bcd(minor),
+        }
+    }
+}
+
+impl Display for FirmwareVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+impl Serialize for FirmwareVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+bitflags! {
+    /// Flags from the ATTRIBUTE key
+    #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    pub struct Attributes: u32 {
+        const INITIAL_USER_LOGOUT_UNSUPPORTED = 1 << 0;
+        const ENTER_BUTTON_CROSS = 1 << 1;
+        const PS_MOVE_WARNING_DIALOG = 1 << 2;
+        const STEREOSCOPIC_3D = 1 << 3;
+        const SUSPEND_ON_PS_BUTTON = 1 << 4;
+        const ENTER_BUTTON_USER_SELECTED = 1 << 5;
+        const OVERRIDE_SHARE_MENU = 1 << 6;
+        const SUSPEND_ON_SPECIAL_RESOLUTION = 1 << 8;
+        const HDCP_ENABLED = 1 << 9;
+        const HDCP_DISABLED_NON_GAME = 1 << 10;
+        const PS_VR_SUPPORTED = 1 << 14;
+        const PS4_PRO_SUPPORTED = 1 << 23;
+
+        const _ = !0;
+    }
+}
+
+impl Serialize for Attributes {
+    /// Serialized as the list of known flag names. Unknown bits are kept in `raw`
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            raw: u32,
+            flags: Vec<&'a str>,
+        }
+        Repr {
+            raw: self.bits(),
+            flags: self.iter_names().map(|(name, _)| name).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'a> ParamSfo<'a> {
+    pub fn new(psf: &'a PSF) -> Self {
+        Self { psf }
+    }
+
+    fn text(&self, key: &str) -> Option<&'a str> {
+        match self.psf.entries.get(key)? {
+            Value::Text(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    fn integer(&self, key: &str) -> Option<i32> {
+        match self.psf.entries.get(key)? {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn title_id(&self) -> Option<&'a str> {
+        self.text("TITLE_ID")
+    }
+
+    pub fn title(&self) -> Option<&'a str> {
+        self.text("TITLE")
+    }
+
+    pub fn localized_title(&self, language: Language) -> Option<&'a str> {
+        self.text(&language.title_key())
+    }
+
+    pub fn app_ver(&self) -> Option<&'a str> {
+        self.text("APP_VER")
+    }
+
+    pub fn version(&self) -> Option<&'a str> {
+        self.text("VERSION")
+    }
+
+    pub fn category(&self) -> Option<Category> {
+        self.text("CATEGORY").map(Category::from)
+    }
+
+    pub fn content_id(&self) -> Option<&'a str> {
+        self.text("CONTENT_ID")
+    }
+
+    pub fn system_ver(&self) -> Option<FirmwareVersion> {
+        self.integer("SYSTEM_VER")
+            .map(|v| FirmwareVersion::from_system_ver(v as u32))
+    }
+
+    pub fn attribute(&self) -> Option<Attributes> {
+        self.integer("ATTRIBUTE")
+            .map(|v| Attributes::from_bits_retain(v as u32))
+    }
+
+    pub fn parental_level(&self) -> Option<u32> {
+        self.integer("PARENTAL_LEVEL").map(|v| v as u32)
+    }
+
+    /// Languages with a localized title
+    pub fn supported_languages(&self) -> Vec<Language> {
+        use strum::IntoEnumIterator;
+        Language::iter()
+            .filter(|l| self.localized_title(*l).is_some())
+            .collect()
+    }
+}
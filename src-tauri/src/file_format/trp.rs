@@ -0,0 +1,528 @@
+use crate::file_format::trp::file_format::*;
+use aes::Aes128;
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::{BlockDecryptMut, BlockEncrypt, KeyInit, KeyIvInit};
+use quick_xml::events::{BytesStart, Event};
+use serde::Serialize;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use zerocopy::FromBytes;
+
+/// Based on
+/// https://github.com/shadps4-emu/shadPS4/blob/b41664ac616894686e072ede61c609b422d79ed4/src/core/file_format/trp.h
+mod file_format {
+    use static_assertions::assert_eq_size;
+    use zerocopy::big_endian::{U32, U64};
+    use zerocopy_derive::{FromBytes, Immutable, KnownLayout};
+
+    pub(super) const TRP_MAGIC: u32 = 0xDCA24D00;
+
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct FileHeader {
+        pub magic: U32,
+        pub version: U32,
+        pub file_size: U64,
+        pub entry_num: U32,
+        pub entry_size: U32,
+        pub dev_flag: U32,
+        pub digest: [u8; 20],
+        pub key_index: U32,
+        pub padding: [u8; 44],
+    }
+    assert_eq_size!(FileHeader, [u8; 0x60]);
+
+    #[repr(C)]
+    #[derive(FromBytes, KnownLayout, Immutable)]
+    pub(super) struct RawEntry {
+        pub name: [u8; 32],
+        pub pos: U64,
+        pub len: U64,
+        pub flag: U32,
+        pub padding: [u8; 12],
+    }
+    assert_eq_size!(RawEntry, [u8; 0x40]);
+
+    /// Trophy configs, in order of preference. The .ESFM ones are only read with an [`EsfmKey`].
+    /// TROP has the names and details while TROPCONF only has the trophy list
+    ///
+    /// [`EsfmKey`]: super::EsfmKey
+    pub(super) const CONFIG_ENTRIES: &[&str] =
+        &["TROP.SFM", "TROPCONF.SFM", "TROP.ESFM", "TROPCONF.ESFM"];
+
+    /// The np communication ids in sce_sys/npbind.dat, one per trophy archive
+    pub(super) const NPBIND_COMM_ID_OFFSET: u64 = 0x84;
+    pub(super) const NPBIND_ENTRY_SIZE: u64 = 0x180;
+    pub(super) const NP_COMM_ID_LEN: usize = 12;
+
+    /// .ESFM entries start with the IV of their content
+    pub(super) const ESFM_IV_LEN: u64 = 16;
+}
+
+/// Entries bigger than this are not read into memory
+const MAX_IN_MEMORY_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    #[error("file is too small to be a TRP: {0} bytes")]
+    FileTooSmall(u64),
+    #[error("invalid TRP header magic code: {0:X}")]
+    InvalidMagicCode(u32),
+    #[error("invalid entry size: {0}")]
+    InvalidEntrySize(u32),
+    #[error("entry table with {0} entries is out of the file")]
+    EntryTableOutOfBounds(u32),
+    #[error("entry {0} is out of the file: offset={1:X}, size={2:X}")]
+    EntryOutOfBounds(String, u64, u64),
+    #[error("entry not found: {0}")]
+    EntryNotFound(String),
+    #[error("entry {0} is encrypted")]
+    EntryEncrypted(String),
+    #[error("entry {0} can't be decrypted: {1} bytes is not a whole number of blocks")]
+    InvalidEncryptedSize(String, u64),
+    #[error("invalid trophy key, expected 32 hex digits")]
+    InvalidTrophyKey,
+    #[error("entry {0} is too large to be loaded: {1} bytes")]
+    EntryTooLarge(String, u64),
+    #[error("invalid trophy config: {0}")]
+    InvalidConfig(#[from] quick_xml::Error),
+    #[error("invalid trophy config attribute: {0}")]
+    InvalidConfigAttr(#[from] quick_xml::events::attributes::AttrError),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+    pub flag: u32,
+    /// .ESFM files are encrypted with the title's trophy key
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Grade {
+    Platinum,
+    Gold,
+    Silver,
+    Bronze,
+    #[default]
+    Unknown,
+}
+
+impl From<&str> for Grade {
+    fn from(value: &str) -> Self {
+        match value {
+            "P" => Grade::Platinum,
+            "G" => Grade::Gold,
+            "S" => Grade::Silver,
+            "B" => Grade::Bronze,
+            _ => Grade::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trophy {
+    pub id: u32,
+    pub hidden: bool,
+    pub grade: Grade,
+    /// Trophy unlocked when this one is, usually the platinum
+    pub parent_id: Option<u32>,
+    pub group_id: Option<u32>,
+    pub name: Option<String>,
+    pub detail: Option<String>,
+    /// Name of the TRP entry with the trophy icon
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrophyGroup {
+    pub id: u32,
+    pub name: Option<String>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrophyConfig {
+    pub np_comm_id: Option<String>,
+    pub trophyset_version: Option<String>,
+    pub title_name: Option<String>,
+    pub title_detail: Option<String>,
+    pub groups: Vec<TrophyGroup>,
+    pub trophies: Vec<Trophy>,
+}
+
+/// Key of the .ESFM entries of a title, derived from the console trophy key
+/// (`TrophyKey` in the emulator config) and the np communication id of the archive
+pub struct EsfmKey([u8; 16]);
+
+impl EsfmKey {
+    pub fn new(trophy_key: &[u8; 16], np_comm_id: &[u8; 16]) -> Self {
+        let mut key = (*np_comm_id).into();
+        Aes128::new(trophy_key.into()).encrypt_block(&mut key);
+        Self(key.into())
+    }
+}
+
+/// Parses the console trophy key, as written in the emulator config
+pub fn parse_trophy_key(hex: &str) -> Result<[u8; 16], Error> {
+    let hex = hex.trim();
+    if hex.len() != 32 || !hex.is_ascii() {
+        return Err(Error::InvalidTrophyKey);
+    }
+    let mut key = [0u8; 16];
+    for (i, b) in key.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| Error::InvalidTrophyKey)?;
+    }
+    Ok(key)
+}
+
+/// Reads the np communication id of the trophy archive `index` (trophy00.trp is 0)
+/// from sce_sys/npbind.dat. It is zero padded to the AES block size
+pub fn read_np_comm_id<R: Read + Seek>(mut npbind: R, index: u32) -> Result<[u8; 16], Error> {
+    npbind.seek(SeekFrom::Start(
+        NPBIND_COMM_ID_OFFSET + index as u64 * NPBIND_ENTRY_SIZE,
+    ))?;
+    let mut id = [0u8; 16];
+    npbind.read_exact(&mut id[..NP_COMM_ID_LEN])?;
+    Ok(id)
+}
+
+/// A trophy archive (e.g. sce_sys/trophy/trophy00.trp)
+pub struct TRP<R> {
+    r: R,
+    entries: Vec<Entry>,
+}
+
+impl TRP<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::read(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> TRP<R> {
+    pub fn read(mut r: R) -> Result<Self, Error> {
+        let file_len = r.seek(SeekFrom::End(0))?;
+        if file_len < size_of::<FileHeader>() as u64 {
+            return Err(Error::FileTooSmall(file_len));
+        }
+
+        r.seek(SeekFrom::Start(0))?;
+        let header = FileHeader::read_from_io(&mut r)?;
+        if header.magic != TRP_MAGIC {
+            return Err(Error::InvalidMagicCode(header.magic.get()));
+        }
+        if header.entry_size.get() as usize != size_of::<RawEntry>() {
+            return Err(Error::InvalidEntrySize(header.entry_size.get()));
+        }
+        let entry_num = header.entry_num.get();
+        if size_of::<FileHeader>() as u64 + entry_num as u64 * size_of::<RawEntry>() as u64
+            > file_len
+        {
+            return Err(Error::EntryTableOutOfBounds(entry_num));
+        }
+
+        let mut entries = Vec::with_capacity(entry_num as usize);
+        for _ in 0..entry_num {
+            let e = RawEntry::read_from_io(&mut r)?;
+            let end = e.name.iter().position(|c| *c == 0).unwrap_or(e.name.len());
+            let name = String::from_utf8_lossy(&e.name[..end]).into_owned();
+            if e.pos.get().checked_add(e.len.get()).is_none_or(|end| end > file_len) {
+                return Err(Error::EntryOutOfBounds(name, e.pos.get(), e.len.get()));
+            }
+            entries.push(Entry {
+                encrypted: name.ends_with(".ESFM"),
+                name,
+                offset: e.pos.get(),
+                size: e.len.get(),
+                flag: e.flag.get(),
+            });
+        }
+
+        Ok(Self { r, entries })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn find_entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    pub fn read_entry(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let entry = self.find_readable_entry(name)?;
+        if entry.encrypted {
+            return Err(Error::EntryEncrypted(name.to_owned()));
+        }
+        self.r.seek(SeekFrom::Start(entry.offset))?;
+        let mut buf = vec![0u8; entry.size as usize];
+        self.r.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Reads and decrypts a .ESFM entry. The padding after the XML document is removed
+    pub fn decrypt_entry(&mut self, name: &str, key: &EsfmKey) -> Result<Vec<u8>, Error> {
+        let entry = self.find_readable_entry(name)?;
+        if !entry.encrypted {
+            return self.read_entry(name);
+        }
+        let len = entry.size.saturating_sub(ESFM_IV_LEN);
+        if entry.size < ESFM_IV_LEN || len % 16 != 0 {
+            return Err(Error::InvalidEncryptedSize(name.to_owned(), entry.size));
+        }
+
+        self.r.seek(SeekFrom::Start(entry.offset))?;
+        let mut iv = [0u8; ESFM_IV_LEN as usize];
+        self.r.read_exact(&mut iv)?;
+        let mut buf = vec![0u8; len as usize];
+        self.r.read_exact(&mut buf)?;
+
+        cbc::Decryptor::<Aes128>::new(&key.0.into(), &iv.into())
+            .decrypt_padded_mut::<NoPadding>(&mut buf)
+            .map_err(|_| Error::InvalidEncryptedSize(name.to_owned(), entry.size))?;
+        let end = buf.iter().rposition(|c| *c == b'>').map_or(0, |i| i + 1);
+        buf.truncate(end);
+        Ok(buf)
+    }
+
+    fn find_readable_entry(&self, name: &str) -> Result<Entry, Error> {
+        let Some(entry) = self.find_entry(name).cloned() else {
+            return Err(Error::EntryNotFound(name.to_owned()));
+        };
+        if entry.size > MAX_IN_MEMORY_ENTRY_SIZE {
+            return Err(Error::EntryTooLarge(name.to_owned(), entry.size));
+        }
+        Ok(entry)
+    }
+
+    /// Decodes the trophy config, if the archive has one. Without `key`, only the
+    /// unencrypted configs are read, which retail archives usually don't have
+    pub fn trophy_config(&mut self, key: Option<&EsfmKey>) -> Result<Option<TrophyConfig>, Error> {
+        let Some(name) = CONFIG_ENTRIES.iter().find(|name| {
+            self.find_entry(name)
+                .is_some_and(|e| !e.encrypted || key.is_some())
+        }) else {
+            return Ok(None);
+        };
+        let data = match key {
+            Some(key) => self.decrypt_entry(name, key)?,
+            None => self.read_entry(name)?,
+        };
+        let mut config = parse_config(&data)?;
+        for t in &mut config.trophies {
+            let icon = format!("TROP{:03}.PNG", t.id);
+            if self.find_entry(&icon).is_some() {
+                t.icon = Some(icon);
+            }
+        }
+        Ok(Some(config))
+    }
+}
+
+fn attr_u32(e: &BytesStart, name: &str) -> Result<Option<u32>, Error> {
+    Ok(attr(e, name)?.and_then(|v| v.trim().parse().ok()))
+}
+
+fn attr(e: &BytesStart, name: &str) -> Result<Option<String>, Error> {
+    let Some(a) = e.try_get_attribute(name)? else {
+        return Ok(None);
+    };
+    Ok(Some(a.unescape_value()?.into_owned()))
+}
+
+/// Parses the trophy config XML:
+/// ```xml
+/// <trophyconf version="1.1" platform="ps4">
+///   <npcommid>NPWR00000_00</npcommid>
+///   <title-name>...</title-name>
+///   <trophy id="000" hidden="no" ttype="P" pid="-1">
+///     <name>...</name>
+///     <detail>...</detail>
+///   </trophy>
+/// </trophyconf>
+/// ```
+fn parse_config(data: &[u8]) -> Result<TrophyConfig, Error> {
+    enum Parent {
+        Root,
+        Group(TrophyGroup),
+        Trophy(Trophy),
+    }
+
+    fn start_element(e: &BytesStart) -> Result<Option<Parent>, Error> {
+        Ok(match e.name().as_ref() {
+            b"group" => Some(Parent::Group(TrophyGroup {
+                id: attr_u32(e, "id")?.unwrap_or_default(),
+                ..Default::default()
+            })),
+            b"trophy" => Some(Parent::Trophy(Trophy {
+                id: attr_u32(e, "id")?.unwrap_or_default(),
+                hidden: attr(e, "hidden")?.as_deref() == Some("yes"),
+                grade: attr(e, "ttype")?.as_deref().unwrap_or_default().into(),
+                // -1 means no parent
+                parent_id: attr_u32(e, "pid")?,
+                group_id: attr_u32(e, "gid")?,
+                ..Default::default()
+            })),
+            _ => None,
+        })
+    }
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.config_mut().trim_text(true);
+
+    let mut config = TrophyConfig::default();
+    let mut parent = Parent::Root;
+    let mut element = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                if let Some(p) = start_element(&e)? {
+                    parent = p;
+                }
+                element = e.name().as_ref().to_vec();
+            }
+            // Groups and trophies without a name or details, e.g. `<trophy id="001" ... />`
+            Event::Empty(e) => match start_element(&e)? {
+                Some(Parent::Group(g)) => config.groups.push(g),
+                Some(Parent::Trophy(t)) => config.trophies.push(t),
+                _ => {}
+            },
+            Event::Text(t) => {
+                let text = Some(t.unescape()?.into_owned());
+                match (&mut parent, element.as_slice()) {
+                    (Parent::Root, b"npcommid") => config.np_comm_id = text,
+                    (Parent::Root, b"trophyset-version") => config.trophyset_version = text,
+                    (Parent::Root, b"title-name") => config.title_name = text,
+                    (Parent::Root, b"title-detail") => config.title_detail = text,
+                    (Parent::Group(g), b"name") => g.name = text,
+                    (Parent::Group(g), b"detail") => g.detail = text,
+                    (Parent::Trophy(t), b"name") => t.name = text,
+                    (Parent::Trophy(t), b"detail") => t.detail = text,
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                match (e.name().as_ref(), std::mem::replace(&mut parent, Parent::Root)) {
+                    (b"group", Parent::Group(g)) => config.groups.push(g),
+                    (b"trophy", Parent::Trophy(t)) => config.trophies.push(t),
+                    (_, p) => parent = p,
+                }
+                element.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(config)
+}
+
+pub mod js {
+    use crate::file_format::trp::{
+        Entry, EsfmKey, TRP, TrophyConfig, parse_trophy_key, read_np_comm_id,
+    };
+    use anyhow::Context;
+    use anyhow_tauri::IntoTAResult;
+    use log::error;
+    use serde::Serialize;
+    use std::fs::File;
+    use std::path::Path;
+    use tauri::AppHandle;
+    use tauri::ipc::Response;
+    use tauri_plugin_fs::{FsExt, OpenOptions, SafeFilePath};
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TrophyList {
+        entries: Vec<Entry>,
+        config: Option<TrophyConfig>,
+    }
+
+    fn open(app: &AppHandle, path: &Path) -> anyhow::Result<File> {
+        Ok(app.fs().open(path, OpenOptions::new().read(true).clone())?)
+    }
+
+    /// The archive is expected in sce_sys/trophy of the game, and its np communication id
+    /// is read from sce_sys/npbind.dat
+    fn esfm_key(app: &AppHandle, path: &Path, trophy_key: &str) -> anyhow::Result<EsfmKey> {
+        let trophy_key = parse_trophy_key(trophy_key)?;
+        let index = path
+            .file_stem()
+            .and_then(|s| s.to_str()?.strip_prefix("trophy")?.parse().ok())
+            .unwrap_or(0);
+        let npbind = path
+            .parent()
+            .and_then(Path::parent)
+            .context("trophy archive is not in a game folder")?
+            .join("npbind.dat");
+        let np_comm_id = read_np_comm_id(open(app, &npbind)?, index)
+            .context("could not read the np communication id")?;
+        Ok(EsfmKey::new(&trophy_key, &np_comm_id))
+    }
+
+    /// `trophy_key` is the `TrophyKey` of the emulator config, needed for the trophy
+    /// names and details of retail games
+    #[tauri::command]
+    pub async fn read_trophies(
+        app: AppHandle,
+        path: SafeFilePath,
+        trophy_key: Option<String>,
+    ) -> anyhow_tauri::TAResult<TrophyList> {
+        tokio::task::spawn_blocking(move || -> anyhow::Result<TrophyList> {
+            let path = path.into_path()?;
+            let mut trp = TRP::read(open(&app, &path)?)
+                .inspect_err(|e| error!("error reading trp file: {}", e))?;
+            let key = trophy_key
+                .filter(|k| !k.is_empty())
+                .map(|k| esfm_key(&app, &path, &k))
+                .transpose()
+                .inspect_err(|e| error!("could not get the trophy config key: {:#}", e))?;
+            let config = trp
+                .trophy_config(key.as_ref())
+                .inspect_err(|e| error!("could not read trophy config: {}", e))?;
+            Ok(TrophyList {
+                entries: trp.entries().to_vec(),
+                config,
+            })
+        })
+        .await
+        .into_ta_result()?
+        .into_ta_result()
+    }
+
+    /// Reads a trophy icon. Only PNG entries can be read
+    #[tauri::command]
+    pub async fn read_trophy_icon(
+        app: AppHandle,
+        path: SafeFilePath,
+        name: String,
+    ) -> anyhow_tauri::TAResult<Response> {
+        if !name.ends_with(".PNG") {
+            return Err(anyhow::anyhow!("entry is not an icon: {}", name)).into_ta_result();
+        }
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Response> {
+            let path = path.into_path()?;
+            let mut trp = TRP::read(open(&app, &path)?)?;
+            let data = trp
+                .read_entry(&name)
+                .inspect_err(|e| error!("could not read trp entry {}: {}", name, e))?;
+            Ok(Response::new(data))
+        })
+        .await
+        .into_ta_result()?
+        .into_ta_result()
+    }
+}
@@ -1,6 +1,11 @@
 pub(crate) mod command;
-mod game_process;
-mod log;
+#[allow(clippy::module_inception)]
+pub(crate) mod game_process;
+pub(crate) mod hub;
+pub mod ipc;
+pub mod log;
+pub mod search;
+pub mod session_log;
 pub mod state;
 
 use crate::game_process::state::GameBridge;
@@ -1,178 +1,483 @@
-use crate::game_process::GameBridgeState;
-use crate::game_process::game_process::{GameEvent, GameProcess};
-use crate::game_process::log::{Level, LogEntry};
-use anyhow::anyhow;
+use crate::game_process::game_process::{
+    DEFAULT_IPC_STOP_TIMEOUT, DEFAULT_TERMINATE_TIMEOUT, GameProcess, SessionInfo,
+};
+use crate::game_process::hub::{EventHub, SubscriptionId};
+use crate::game_process::log::{
+    Direction, ExportOptions, Level, LogClass, LogData, LogExporter, LogLimits, LogPage, RowId,
+};
+use crate::game_process::search::{self, SearchQuery, SearchResult};
+use crate::game_process::session_log::{self, SessionLogFile, SessionLogPolicy};
+use crate::game_process::state::SessionId;
+use crate::game_process::{GameBridgeState, ipc};
+use anyhow::{Context, anyhow};
 use anyhow_tauri::IntoTAResult;
 use anyhow_tauri::bail;
-use log::{debug, error};
+use log::{debug, error, info};
 use std::ffi::OsString;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::Mutex;
 use tauri::ipc::Channel;
 use tauri_plugin_dialog::DialogExt;
-use tauri_plugin_fs::FilePath;
-use time::macros::format_description;
+use tauri_plugin_fs::{FilePath, FsExt};
+use tauri_plugin_opener::OpenerExt;
+
+const DEFAULT_LOG_PAGE: usize = 1000;
+const MAX_LOG_PAGE: usize = 10_000;
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn game_process_spawn(
     app_handle: tauri::AppHandle,
     state: GameBridgeState<'_>,
     exe: FilePath,
     wd: FilePath,
     args: Vec<String>,
-    on_event: Channel<GameEvent<'static>>,
-    copy_data_from_pid: Option<u32>,
-) -> anyhow_tauri::TAResult<u32> {
-    let args: Vec<OsString> = args.into_iter().map(|s| OsString::from(s)).collect();
+    on_event: Channel,
+    copy_data_from: Option<SessionId>,
+    game_path: Option<PathBuf>,
+) -> anyhow_tauri::TAResult<SessionId> {
+    let args: Vec<OsString> = args.into_iter().map(OsString::from).collect();
 
     let mut data = None;
-    if let Some(old_pid) = copy_data_from_pid {
+    if let Some(old_session) = copy_data_from {
         let state = state.lock().await;
-        let Some(old_proc) = state.process_list.get(&old_pid) else {
+        let Some(old_proc) = state.process_list.get(&old_session) else {
             bail!("old process not found");
         };
         data = Some(old_proc.data().clone());
     }
 
+    let title_id = game_path.as_deref().and_then(session_log::title_id);
+
+    let events = EventHub::new();
+    events.attach(on_event);
+
     let p = GameProcess::start(
         &app_handle,
         exe.as_path().ok_or(anyhow!("invalid exe"))?,
         wd.as_path().ok_or(anyhow!("invalid wd"))?,
         args,
-        move |ev| {
-            on_event.send(ev).expect("could not send game event to js");
-        },
+        events,
         data,
+        title_id.as_deref(),
+        game_path.as_deref(),
     )
     .await
     .inspect_err(|e| error!("could not start the game: err={}", e))?;
 
-    Ok(p.pid())
+    Ok(p.id())
 }
 
 #[tauri::command]
-pub async fn game_process_kill(state: GameBridgeState<'_>, pid: u32) -> anyhow_tauri::TAResult<()> {
+pub async fn game_process_list(
+    state: GameBridgeState<'_>,
+) -> anyhow_tauri::TAResult<Vec<SessionInfo>> {
     let state = state.lock().await;
-    let Some(proc) = state.process_list.get(&pid) else {
-        debug!("process not found: pid={}", pid);
-        bail!("pid not found");
+    let mut sessions = Vec::with_capacity(state.process_list.len());
+    for proc in state.process_list.values() {
+        sessions.push(proc.info().await);
+    }
+    sessions.sort_by_key(|s| s.id);
+    Ok(sessions)
+}
+
+/// Sends the log from `from_row_id` (all of it by default) and then the live events to `on_event`
+#[tauri::command]
+pub async fn game_process_subscribe(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    on_event: Channel,
+    from_row_id: Option<RowId>,
+) -> anyhow_tauri::TAResult<SubscriptionId> {
+    let Some(proc) = state.lock().await.process_list.get(&session_id).cloned() else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    Ok(proc.subscribe(on_event, from_row_id.unwrap_or(0)).await)
+}
+
+#[tauri::command]
+pub async fn game_process_unsubscribe(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    subscription_id: SubscriptionId,
+) -> anyhow_tauri::TAResult<()> {
+    let mut state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    if !proc.unsubscribe(subscription_id) {
+        debug!(
+            "subscription not found: subscription_id={}",
+            subscription_id
+        );
+    }
+    // The last window left after the game exited, nobody else would delete the session
+    if proc.subscription_count() == 0 && proc.has_exited().await {
+        info!("Removing session {}, no window is subscribed", session_id);
+        state.process_list.remove(&session_id);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn game_process_kill(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+) -> anyhow_tauri::TAResult<()> {
+    let state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
     };
 
     proc.kill().await?;
     Ok(())
 }
 
+/// Stops the game gracefully, see [`GameProcess::stop`]. The result arrives as a `GameExit` event
+#[tauri::command]
+pub async fn game_process_stop(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    ipc_timeout_ms: Option<u64>,
+    term_timeout_ms: Option<u64>,
+) -> anyhow_tauri::TAResult<()> {
+    let state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    let ipc_timeout = ipc_timeout_ms.map_or(DEFAULT_IPC_STOP_TIMEOUT, Duration::from_millis);
+    let term_timeout = term_timeout_ms.map_or(DEFAULT_TERMINATE_TIMEOUT, Duration::from_millis);
+    proc.stop(ipc_timeout, term_timeout).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn game_process_send_ipc(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    command: ipc::Command,
+) -> anyhow_tauri::TAResult<()> {
+    let state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    proc.send_command(&command).await?;
+    Ok(())
+}
+
 #[tauri::command]
-pub async fn game_process_send(
+pub async fn game_process_patch_memory(
     state: GameBridgeState<'_>,
-    pid: u32,
-    value: &str,
+    session_id: SessionId,
+    patch: ipc::MemoryPatch,
 ) -> anyhow_tauri::TAResult<()> {
     let state = state.lock().await;
-    let Some(proc) = state.process_list.get(&pid) else {
-        debug!("process not found: pid={}", pid);
-        bail!("pid not found");
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
     };
 
-    proc.send(value).await?;
+    proc.send_command(&ipc::Command::PatchMemory(patch))
+        .await
+        .inspect_err(|e| error!("could not patch memory: err={}", e))?;
+    Ok(())
+}
+
+/// Applies to the games started from now on
+#[tauri::command]
+pub async fn game_process_set_log_limits(
+    state: GameBridgeState<'_>,
+    limits: LogLimits,
+) -> anyhow_tauri::TAResult<()> {
+    state.lock().await.log_limits = limits;
+    Ok(())
+}
+
+/// Applies to the games started from now on
+#[tauri::command]
+pub async fn game_process_set_session_log_policy(
+    state: GameBridgeState<'_>,
+    policy: SessionLogPolicy,
+) -> anyhow_tauri::TAResult<()> {
+    state.lock().await.session_log_policy = policy;
+    Ok(())
+}
+
+/// The logs of the past sessions of a game, newest first
+#[tauri::command]
+pub async fn game_process_list_session_logs(
+    state: GameBridgeState<'_>,
+    title_id: String,
+) -> anyhow_tauri::TAResult<Vec<SessionLogFile>> {
+    let dir = state.lock().await.title_log_dir(&title_id);
+    session_log::list(&dir)
+        .inspect_err(|e| error!("could not list the session logs: err={:#}", e))
+        .into_ta_result()
+}
+
+#[tauri::command]
+pub async fn game_process_open_session_log(
+    app_handle: tauri::AppHandle,
+    state: GameBridgeState<'_>,
+    title_id: String,
+    name: String,
+) -> anyhow_tauri::TAResult<()> {
+    let dir = state.lock().await.title_log_dir(&title_id);
+    let path = session_log::file_path(&dir, &name)?;
+    if !path.exists() {
+        bail!("session log not found");
+    }
+
+    #[cfg(target_os = "linux")]
+    let with = Some("xdg-open");
+    #[cfg(not(target_os = "linux"))]
+    let with = None::<&str>;
+    if let Err(e) = app_handle.opener().open_path(path.to_string_lossy(), with) {
+        error!(
+            "could not open the session log: path={}, err={}",
+            path.display(),
+            e
+        );
+        bail!("could not open the session log");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn game_process_delete_session_log(
+    state: GameBridgeState<'_>,
+    title_id: String,
+    name: String,
+) -> anyhow_tauri::TAResult<()> {
+    let state = state.lock().await;
+    let dir = state.title_log_dir(&title_id);
+    let path = session_log::file_path(&dir, &name)?;
+
+    // Held until the file is deleted, so no session starts writing it meanwhile
+    for proc in state.process_list.values() {
+        let exited = proc.has_exited().await;
+        let mut log_data = proc.data().log_data.lock().await;
+        if !log_data.session_log().is_some_and(|log| log.owns(&path)) {
+            continue;
+        }
+        if !exited {
+            debug!("session log is in use: path={}", path.display());
+            bail!("the session log is still being written");
+        }
+        // The session has nothing left to log
+        log_data.stop_session_log();
+    }
+
+    if let Err(e) = fs::remove_file(&path) {
+        error!(
+            "could not delete the session log: path={}, err={}",
+            path.display(),
+            e
+        );
+        bail!("could not delete the session log");
+    }
     Ok(())
 }
 
 #[tauri::command]
 pub async fn game_process_delete(
     state: GameBridgeState<'_>,
-    pid: u32,
+    session_id: SessionId,
 ) -> anyhow_tauri::TAResult<()> {
     let mut state = state.lock().await;
-    let res = state.process_list.remove(&pid);
+    let res = state.process_list.remove(&session_id);
     if res.is_none() {
-        debug!("process not found: pid={}", pid);
-        bail!("pid not found");
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
     }
     Ok(())
 }
 
+/// Rows of the log are fetched a page at a time, forward from `from_row_id` (the first row by default)
+/// or backward from it (the last row by default)
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn game_process_get_log(
     state: GameBridgeState<'_>,
-    pid: u32,
+    session_id: SessionId,
     level: Option<Vec<Level>>,
-    log_class: Option<Vec<&str>>,
-) -> anyhow_tauri::TAResult<String> {
+    log_class: Option<Vec<String>>,
+    from_row_id: Option<RowId>,
+    limit: Option<usize>,
+    direction: Option<Direction>,
+) -> anyhow_tauri::TAResult<LogPage> {
     let state = state.lock().await;
-    let Some(proc) = state.process_list.get(&pid) else {
-        debug!("process not found: pid={}", pid);
-        bail!("pid not found");
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
     };
 
     let log_class = log_class.unwrap_or_default();
     let level = level.unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_LOG_PAGE).clamp(1, MAX_LOG_PAGE);
 
-    let log_data = proc.data().log_data.lock().await;
-    let rows = log_data
-        .rows
-        .iter()
-        .filter(|(_, e)| level.is_empty() || level.contains(&e.level))
-        .filter(|(_, e)| log_class.is_empty() || log_class.contains(&e.class))
-        .map(|(i, e)| (*i, e).into())
-        .collect::<Vec<LogEntry>>();
+    // Spilled rows are read back from disk
+    let log_data = proc.data().log_data.clone();
+    drop(state);
+    tokio::task::spawn_blocking(move || {
+        log_data
+            .blocking_lock()
+            .page(from_row_id, limit, direction.unwrap_or_default(), |e| {
+                (level.is_empty() || level.contains(&e.level))
+                    && (log_class.is_empty() || log_class.iter().any(|c| **c == *e.class))
+            })
+    })
+    .await
+    .into_ta_result()?
+    .into_ta_result()
+}
 
-    Ok(serde_json::to_string(&rows).into_ta_result()?)
+#[tauri::command]
+pub async fn game_process_search_log(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    query: SearchQuery,
+) -> anyhow_tauri::TAResult<SearchResult> {
+    let state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    // Neither the sessions nor the log are held while the rows are read
+    let log_data = proc.data().log_data.clone();
+    drop(state);
+    tokio::task::spawn_blocking(move || search::search(&log_data.blocking_lock(), &query))
+        .await
+        .into_ta_result()?
+        .inspect_err(|e| debug!("could not search the log: err={}", e))
+        .into_ta_result()
+}
+
+#[tauri::command]
+pub async fn game_process_get_log_classes(
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+) -> anyhow_tauri::TAResult<Vec<LogClass>> {
+    let state = state.lock().await;
+    let Some(proc) = state.process_list.get(&session_id) else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    Ok(proc.data().log_data.lock().await.class_counts())
 }
 
 #[tauri::command]
 pub async fn game_process_save_log(
     app_handle: tauri::AppHandle,
     state: GameBridgeState<'_>,
-    pid: u32,
+    session_id: SessionId,
     default_name: String,
     level: Option<Vec<Level>>,
-    log_class: Option<Vec<&str>>,
+    log_class: Option<Vec<String>>,
+    options: Option<ExportOptions>,
 ) -> anyhow_tauri::TAResult<()> {
-    let state = state.lock().await;
-    let Some(proc) = state.process_list.get(&pid) else {
-        debug!("process not found: pid={}", pid);
-        bail!("pid not found");
-    };
+    if !state.lock().await.process_list.contains_key(&session_id) {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    }
 
+    let exporter = LogExporter::new(options.unwrap_or_default())
+        .levels(level.unwrap_or_default())
+        .classes(log_class.unwrap_or_default());
+    let extension = exporter.options().extension();
+    let default_name = Path::new(&default_name).with_extension(extension);
+    // The dialogs only match the last extension
+    let dialog_filter = extension.rsplit('.').next().unwrap_or(extension);
     let Some(path) = app_handle
         .dialog()
         .file()
-        .set_file_name(default_name)
-        .add_filter("shadPS4 Log", &["txt"])
+        .set_file_name(default_name.to_string_lossy())
+        .add_filter("shadPS4 Log", &[dialog_filter])
         .blocking_save_file()
     else {
         return Ok(());
     };
     let path = path.into_path().map_err(|e| anyhow!("invalid path: err={}", e))?;
 
-    let Ok(mut file) = File::create(path) else {
-        bail!("Could not open the file for writing");
+    // The sessions are not held while the dialog is open, it may have been deleted meanwhile
+    let Some(log_data) = state
+        .lock()
+        .await
+        .process_list
+        .get(&session_id)
+        .map(|proc| proc.data().log_data.clone())
+    else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
     };
+    export_log(log_data, path, exporter)
+        .await
+        .inspect_err(|e| error!("could not save the log: err={:#}", e))?;
+    Ok(())
+}
 
-    let log_class = log_class.unwrap_or_default();
-    let level = level.unwrap_or_default();
-    let log_data = proc.data().log_data.lock().await;
-    let rows = log_data
-        .rows
-        .iter()
-        .filter(|(_, e)| level.is_empty() || level.contains(&e.level))
-        .filter(|(_, e)| log_class.is_empty() || log_class.contains(&e.class));
-
-    let line_time_fmt = format_description!("[hour]:[minute]:[second]");
-    for (_, row) in rows {
-        let r = write!(
-            &mut file,
-            "{} [{}] <{}> {}\n",
-            row.time.format(line_time_fmt).unwrap(),
-            row.class,
-            row.level.as_ref(),
-            &row.message
-        );
-        if let Err(err) = r {
-            bail!("could not write to file: err={}", err);
-        }
+/// Same as `game_process_save_log`, without a dialog. Returns the number of rows written
+#[tauri::command]
+pub async fn game_process_export_log(
+    app_handle: tauri::AppHandle,
+    state: GameBridgeState<'_>,
+    session_id: SessionId,
+    path: FilePath,
+    level: Option<Vec<Level>>,
+    log_class: Option<Vec<String>>,
+    options: Option<ExportOptions>,
+) -> anyhow_tauri::TAResult<usize> {
+    let path = path.into_path().map_err(|e| anyhow!("invalid path: err={}", e))?;
+    if !app_handle.fs_scope().is_allowed(&path) {
+        debug!("export path not allowed: path={}", path.display());
+        bail!("path not allowed");
     }
 
-    Ok(())
+    // Only the log is held while the rows are written
+    let Some(log_data) = state
+        .lock()
+        .await
+        .process_list
+        .get(&session_id)
+        .map(|proc| proc.data().log_data.clone())
+    else {
+        debug!("session not found: session_id={}", session_id);
+        bail!("session not found");
+    };
+
+    let exporter = LogExporter::new(options.unwrap_or_default())
+        .levels(level.unwrap_or_default())
+        .classes(log_class.unwrap_or_default());
+    let rows = export_log(log_data, path, exporter)
+        .await
+        .inspect_err(|e| error!("could not export the log: err={:#}", e))?;
+    Ok(rows)
+}
+
+/// Writes the log from a blocking thread, spilled rows are read back from disk
+async fn export_log(
+    log_data: Arc<Mutex<LogData>>,
+    path: PathBuf,
+    exporter: LogExporter,
+) -> anyhow::Result<usize> {
+    tokio::task::spawn_blocking(move || {
+        let file = File::create(&path).context("could not open the file for writing")?;
+        exporter.export(&log_data.blocking_lock(), BufWriter::new(file))
+    })
+    .await?
 }
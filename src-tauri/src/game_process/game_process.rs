@@ -1,36 +1,150 @@
-use crate::game_process::log::{Entry, LogData, LogEntry};
+use crate::game_process::hub::{EventHub, SubscriptionId};
+use crate::game_process::ipc::{self, Capability, IPC_PREFIX, MemoryPatch};
+use crate::game_process::log::{LogData, LogEntry, RowId};
+use crate::game_process::state::SessionId;
 use crate::game_process::{GameBridgeStateType, log};
+use crate::library::stats::PlaySession;
+use ::log::{error, info};
 use anyhow::Context;
 use serde::Serialize;
 use std::ffi::OsStr;
-use std::path::Path;
-use std::process::Stdio;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::async_runtime::Mutex;
+use tauri::ipc::{Channel, InvokeResponseBody, IpcResponse};
 use tauri::{AppHandle, Manager};
 use time::OffsetDateTime;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 use tokio::sync::mpsc::{Sender, channel};
+use tokio::time::{Instant, sleep_until};
+
+/// Log rows read at once when replaying the log to a new subscription
+const REPLAY_CHUNK_ROWS: usize = 1024;
+
+/// Default time the emulator has to exit after the IPC `STOP` command
+pub const DEFAULT_IPC_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default time the emulator has to exit after SIGTERM, before it is killed
+pub const DEFAULT_TERMINATE_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "event")]
 pub enum GameEvent<'a> {
     Log(LogEntry<'a>),
     AddLogClass { value: &'a str },
-    GameExit { status: i32 },
+    GameExit(ExitInfo),
     IOError { err: String },
-    IpcLine { value: &'a str },
+    IpcEnabled,
+    Capabilities { capabilities: &'a [Capability] },
+    RestartRequested { args: &'a [String] },
+    Restarted { old_pid: u32, new_pid: u32 },
+    IpcError { err: String },
+    Lagged { skipped: u64 },
 }
 
 enum InnerCommand {
     Kill,
+    Stop {
+        ipc_timeout: Duration,
+        term_timeout: Duration,
+    },
+}
+
+/// The last step taken to stop the emulator before it exited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExitStage {
+    /// Exited on its own
+    Exited,
+    /// Exited after the IPC `STOP` command
+    Ipc,
+    /// Exited after SIGTERM
+    Terminate,
+    /// Killed
+    Kill,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitInfo {
+    /// Exit code, missing if the process was ended by a signal
+    pub status: Option<i32>,
+    /// Signal that ended the process (Unix only)
+    pub signal: Option<i32>,
+    pub stage: ExitStage,
+}
+
+impl ExitInfo {
+    fn new(exit_status: ExitStatus, stage: ExitStage) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&exit_status);
+        #[cfg(not(unix))]
+        let signal = None;
+        Self {
+            status: exit_status.code(),
+            signal,
+            stage,
+        }
+    }
+
+    /// Exit code in the shell convention, `128 + signal` for signals
+    pub fn code(&self) -> i32 {
+        self.status.or(self.signal.map(|s| 128 + s)).unwrap_or(-1)
+    }
+}
+
+impl fmt::Display for ExitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.status, self.signal) {
+            (Some(status), _) => write!(f, "status {}", status),
+            (None, Some(signal)) => write!(f, "signal {}", signal),
+            (None, None) => write!(f, "unknown status"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum SessionState {
+    Running,
+    Exited(ExitInfo),
+}
+
+struct ProcessStatus {
+    /// Changes when the emulator restarts
+    pid: u32,
+    state: SessionState,
+}
+
+/// Listed by `game_process_list`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub pid: u32,
+    pub exe: PathBuf,
+    pub wd: PathBuf,
+    #[serde(with = "time::serde::timestamp")]
+    pub started_at: OffsetDateTime,
+    #[serde(flatten)]
+    pub state: SessionState,
 }
 
 #[derive(Clone)]
 pub struct GameProcess {
-    pid: u32,
+    id: SessionId,
+    exe: PathBuf,
+    wd: PathBuf,
+    started_at: OffsetDateTime,
+    status: Arc<Mutex<ProcessStatus>>,
     data: ProcessData,
+    events: Arc<EventHub>,
+
+    ipc: Arc<Mutex<IpcState>>,
 
     sender: Arc<Mutex<Sender<String>>>, // These are commands sent to the emulator
     inner_sender: Arc<Mutex<Sender<InnerCommand>>>, // These are commands sent to the launcher
@@ -41,46 +155,126 @@ pub struct ProcessData {
     pub log_data: Arc<Mutex<LogData>>,
 }
 
+#[derive(Default)]
+struct IpcState {
+    /// Advertised by the emulator during the IPC handshake
+    capabilities: Vec<Capability>,
+    /// Applied again when the emulator restarts itself
+    memory_patches: Vec<MemoryPatch>,
+}
+
+/// Spawns the emulator, again with new arguments when it asks for a restart
+struct Spawner {
+    exe: PathBuf,
+    wd: PathBuf,
+}
+
+impl Spawner {
+    fn spawn<S: AsRef<OsStr>>(&self, args: impl IntoIterator<Item = S>) -> std::io::Result<Child> {
+        Command::new(self.exe.as_os_str())
+            .current_dir(&self.wd)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .env("SHADPS4_ENABLE_IPC", "true")
+            .spawn()
+    }
+}
+
+type Pipes = (
+    ChildStdin,
+    Lines<BufReader<ChildStdout>>,
+    Lines<BufReader<ChildStderr>>,
+);
+
+fn take_pipes(c: &mut Child) -> Pipes {
+    let stdin = c.stdin.take().expect("stdin is piped");
+    let stdout = c.stdout.take().expect("stdout is piped");
+    let stderr = c.stderr.take().expect("stderr is piped");
+    (
+        stdin,
+        BufReader::new(stdout).lines(),
+        BufReader::new(stderr).lines(),
+    )
+}
+
 impl GameProcess {
-    pub async fn start<'b, S>(
-        app_handle: &'b AppHandle,
+    /// `title_id` names the folder of the session log, unless `data` comes from an older session.
+    /// A play session is recorded for `game_path` once the emulator is running
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start<S>(
+        app_handle: &AppHandle,
         exe: impl AsRef<Path>,
         wd: impl AsRef<Path>,
         args: impl IntoIterator<Item = S>,
-        callback: impl Fn(GameEvent) + Send + 'static,
+        events: EventHub,
         data: Option<ProcessData>,
+        title_id: Option<&str>,
+        game_path: Option<&Path>,
     ) -> anyhow::Result<GameProcess>
     where
         S: AsRef<OsStr>,
     {
-        let c = Command::new(exe.as_ref().as_os_str())
-            .current_dir(wd)
-            .args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .env("SHADPS4_ENABLE_IPC", "true")
-            .spawn()?;
+        let spawner = Spawner {
+            exe: exe.as_ref().to_path_buf(),
+            wd: wd.as_ref().to_path_buf(),
+        };
+        let c = spawner.spawn(args)?;
+
+        let status = Arc::new(Mutex::new(ProcessStatus {
+            pid: c.id().context("failed to get process id")?,
+            state: SessionState::Running,
+        }));
+
+        let session = match game_path {
+            Some(game_path) => PlaySession::begin(app_handle, game_path)
+                .await
+                .inspect_err(|e| error!("could not start play session: {}", e))
+                .ok()
+                .flatten(),
+            None => None,
+        };
 
-        let pid = c.id().expect("failed to get process id");
+        let exe = spawner.exe.clone();
+        let wd = spawner.wd.clone();
+        let ipc = Arc::new(Mutex::new(IpcState::default()));
+        let events = Arc::new(events);
 
+        // Held until the session is listed, the event task may remove it as soon as it exits
+        let state = app_handle.state::<GameBridgeStateType>();
+        let mut state = state.lock().await;
+        let id = state.next_session_id();
         let data = data.unwrap_or_else(|| ProcessData {
-            log_data: Arc::new(Mutex::new(LogData::new())),
+            log_data: Arc::new(Mutex::new(state.new_log_data(id, title_id))),
         });
-
-        let (sender, inner_sender) =
-            Self::handle_events(c, app_handle.clone(), callback, data.clone()).await;
+        let (sender, inner_sender) = Self::handle_events(
+            app_handle.clone(),
+            id,
+            c,
+            spawner,
+            events.clone(),
+            data.clone(),
+            ipc.clone(),
+            status.clone(),
+            session,
+        )
+        .await;
 
         let process = GameProcess {
-            pid,
+            id,
+            exe,
+            wd,
+            started_at: OffsetDateTime::now_utc(),
+            status,
+            ipc,
             sender: Arc::new(Mutex::new(sender)),
             inner_sender: Arc::new(Mutex::new(inner_sender)),
             data,
+            events,
         };
 
-        let state = app_handle.state::<GameBridgeStateType>();
-        let mut state = state.lock().await;
-        state.process_list.insert(pid, process.clone());
+        state.process_list.insert(process.id, process.clone());
         drop(state);
 
         Ok(process)
@@ -88,14 +282,65 @@ impl GameProcess {
 }
 
 impl GameProcess {
-    pub fn pid(&self) -> u32 {
-        self.pid
+    pub fn id(&self) -> SessionId {
+        self.id
+    }
+
+    pub async fn info(&self) -> SessionInfo {
+        let status = self.status.lock().await;
+        SessionInfo {
+            id: self.id,
+            pid: status.pid,
+            exe: self.exe.clone(),
+            wd: self.wd.clone(),
+            started_at: self.started_at,
+            state: status.state.clone(),
+        }
     }
 
     pub fn data(&self) -> &ProcessData {
         &self.data
     }
 
+    /// Replays the log classes and the rows from `from_row_id`, then forwards the live events.
+    /// The rows are read and sent a chunk at a time by the subscription task
+    pub async fn subscribe(&self, channel: Channel, from_row_id: RowId) -> SubscriptionId {
+        // Log events are published with the log locked, so the replay ends where the live events start
+        let log_data = self.data.log_data.lock().await;
+        let live = self.events.live();
+        let classes: Vec<_> = log_data
+            .classes()
+            .filter_map(|value| {
+                GameEvent::AddLogClass { value }
+                    .body()
+                    .inspect_err(|e| error!("could not serialize game event: {}", e))
+                    .ok()
+            })
+            .collect();
+        let end = log_data.next_row_id();
+        drop(log_data);
+
+        let replay = replay_rows(
+            self.data.log_data.clone(),
+            channel.clone(),
+            classes,
+            from_row_id..end,
+        );
+        self.events.forward(channel, replay, live)
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.events.unsubscribe(id)
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.events.subscription_count()
+    }
+
+    pub async fn has_exited(&self) -> bool {
+        matches!(self.status.lock().await.state, SessionState::Exited(_))
+    }
+
     pub async fn kill(&self) -> anyhow::Result<()> {
         let inner_sender = self.inner_sender.lock().await;
         inner_sender
@@ -105,7 +350,40 @@ impl GameProcess {
         Ok(())
     }
 
-    pub async fn send(&self, value: &str) -> anyhow::Result<()> {
+    /// Asks the emulator to exit, escalating from the IPC `STOP` command to SIGTERM and then SIGKILL
+    pub async fn stop(&self, ipc_timeout: Duration, term_timeout: Duration) -> anyhow::Result<()> {
+        let inner_sender = self.inner_sender.lock().await;
+        inner_sender
+            .send(InnerCommand::Stop {
+                ipc_timeout,
+                term_timeout,
+            })
+            .await
+            .context("receiver is closed")?;
+        Ok(())
+    }
+
+    pub async fn has_capability(&self, capability: &Capability) -> bool {
+        self.ipc.lock().await.capabilities.contains(capability)
+    }
+
+    /// Keeps the patches applied at launch, so they're sent again if the emulator restarts
+    pub async fn set_memory_patches(&self, patches: Vec<MemoryPatch>) {
+        self.ipc.lock().await.memory_patches = patches;
+    }
+
+    /// Sends an IPC command. Memory patches are validated and require [`Capability::EnableMemoryPatch`]
+    pub async fn send_command(&self, command: &ipc::Command) -> anyhow::Result<()> {
+        if let ipc::Command::PatchMemory(patch) = command {
+            if !self.has_capability(&Capability::EnableMemoryPatch).await {
+                return Err(ipc::Error::MissingCapability("ENABLE_MEMORY_PATCH").into());
+            }
+            patch.validate()?;
+        }
+        self.send(&command.encode()).await
+    }
+
+    async fn send(&self, value: &str) -> anyhow::Result<()> {
         let sender = self.sender.lock().await;
         sender
             .send(value.to_string())
@@ -114,83 +392,144 @@ impl GameProcess {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_events(
+        app_handle: AppHandle,
+        id: SessionId,
         mut c: Child,
-        _app_handle: AppHandle,
-        callback: impl Fn(GameEvent) + Send + 'static,
+        spawner: Spawner,
+        events: Arc<EventHub>,
         data: ProcessData,
+        ipc_state: Arc<Mutex<IpcState>>,
+        status: Arc<Mutex<ProcessStatus>>,
+        session: Option<PlaySession>,
     ) -> (Sender<String>, Sender<InnerCommand>) {
         let (tx, mut rx) = channel::<String>(1);
         let (inner_tx, mut inner_rx) = channel::<InnerCommand>(1);
 
-        // let pid = c.id().expect("failed to get process id");
-
         tauri::async_runtime::spawn(async move {
-            let mut stdin = c.stdin.take().expect("stdin is piped");
-
-            let stdout = c.stdout.take().expect("stdout is piped");
-            let stdout = BufReader::new(stdout);
-            let mut stdout_lines = stdout.lines();
-
-            let stderr = c.stderr.take().expect("stderr is piped");
-            let stderr = BufReader::new(stderr);
-            let mut stderr_lines = stderr.lines();
+            let (mut stdin, mut stdout_lines, mut stderr_lines) = take_pipes(&mut c);
 
             let mut io_err: Option<anyhow::Error> = None;
+            let mut ipc_parser = ipc::Parser::new();
+            // Restarted emulators are started as soon as the handshake is done
+            let mut start_after_handshake = false;
+            let mut ipc_enabled = false;
+            // Output is read until the emulator exits, so closed pipes are no longer polled
+            let mut stdout_open = true;
+            let mut stderr_open = true;
+
+            let mut stage = ExitStage::Exited;
+            // When the current stop stage times out, and how long SIGTERM gets after that
+            let mut deadline: Option<Instant> = None;
+            let mut term_deadline = Duration::ZERO;
 
             loop {
                 tokio::select! {
-                    msg = stdout_lines.next_line() => {
+                    // Read what the emulator wrote before exiting, it may be a restart request
+                    biased;
+                    msg = stderr_lines.next_line(), if stderr_open => {
                        match msg {
                             Err(_) => {
-                                io_err = Some(msg.context("failed to read stdout").unwrap_err());
+                                io_err = Some(msg.context("failed to read stderr").unwrap_err());
                                 break;
                             }
-                            Ok(None) => break,
+                            Ok(None) => stderr_open = false,
                             Ok(Some(line)) => {
+                                if let Some(line) = line.strip_prefix(IPC_PREFIX) {
+                                    match ipc_parser.feed(line) {
+                                        Ok(None) => {}
+                                        Ok(Some(ipc::Message::Enabled)) => {
+                                            ipc_enabled = true;
+                                            events.publish(&GameEvent::IpcEnabled);
+                                        }
+                                        Ok(Some(ipc::Message::Capabilities(capabilities))) => {
+                                            let mut commands = vec![ipc::Command::Run];
+                                            {
+                                                let mut ipc_state = ipc_state.lock().await;
+                                                if std::mem::take(&mut start_after_handshake) {
+                                                    if capabilities.contains(&Capability::EnableMemoryPatch) {
+                                                        commands.extend(ipc_state.memory_patches.iter().cloned().map(ipc::Command::PatchMemory));
+                                                    }
+                                                    commands.push(ipc::Command::Start);
+                                                }
+                                                ipc_state.capabilities = capabilities.clone();
+                                            }
+                                            events.publish(&GameEvent::Capabilities { capabilities: &capabilities });
+                                            for cmd in commands {
+                                                if let Err(err) = write_command(&mut stdin, &cmd.encode()).await {
+                                                    io_err = Some(err);
+                                                    break;
+                                                }
+                                            }
+                                            if io_err.is_some() {
+                                                break;
+                                            }
+                                        }
+                                        // The emulator is being stopped, let it exit
+                                        Ok(Some(ipc::Message::Restart { .. })) if stage != ExitStage::Exited => {}
+                                        Ok(Some(ipc::Message::Restart { args })) => {
+                                            events.publish(&GameEvent::RestartRequested { args: &args });
+
+                                            let old_pid = c.id().unwrap_or_default();
+                                            let _ = write_command(&mut stdin, &ipc::Command::Stop.encode()).await;
+                                            let _ = c.start_kill();
+                                            let _ = c.wait().await;
+
+                                            c = match spawner.spawn(&args) {
+                                                Ok(c) => c,
+                                                Err(err) => {
+                                                    io_err = Some(anyhow::Error::new(err).context("failed to restart the emulator"));
+                                                    break;
+                                                }
+                                            };
+                                            (stdin, stdout_lines, stderr_lines) = take_pipes(&mut c);
+                                            (stdout_open, stderr_open) = (true, true);
+                                            ipc_parser = ipc::Parser::new();
+                                            start_after_handshake = true;
+                                            ipc_enabled = false;
+                                            ipc_state.lock().await.capabilities.clear();
+
+                                            let new_pid = c.id().unwrap_or_default();
+                                            status.lock().await.pid = new_pid;
+                                            info!("Emulator restarted: old_pid={} new_pid={}", old_pid, new_pid);
+                                            events.publish(&GameEvent::Restarted { old_pid, new_pid });
+                                        }
+                                        Err(err) => events.publish(&GameEvent::IpcError { err: err.to_string() }),
+                                    }
+                                    continue;
+                                }
                                 let mut log_data = data.log_data.lock().await;
-                                let (entry, new_class) = log_data.parse_entry(&line).unwrap_or_else(|| {
-                                    (Entry {
-                                        time: OffsetDateTime::now_utc(),
-                                        level: log::Level::Info,
-                                        class: "UNK",
-                                        message: line,
-                                    }, false)
-                                });
+                                let (entry, new_class) = log_data.new_entry(log::Level::Error, "STDERR", line);
                                 if new_class {
-                                    callback(GameEvent::AddLogClass{ value: entry.class })
+                                    events.publish(&GameEvent::AddLogClass{ value: &entry.class })
                                 }
                                 let (row_id, entry) = log_data.add_entry(entry);
-                                callback(GameEvent::Log ((
+                                events.publish(&GameEvent::Log ((
                                     row_id,
                                     entry,
                                 ).into()));
                             },
                         }
                     }
-                    msg = stderr_lines.next_line() => {
+                    msg = stdout_lines.next_line(), if stdout_open => {
                        match msg {
                             Err(_) => {
-                                io_err = Some(msg.context("failed to read stderr").unwrap_err());
+                                io_err = Some(msg.context("failed to read stdout").unwrap_err());
                                 break;
                             }
-                            Ok(None) => break,
+                            Ok(None) => stdout_open = false,
                             Ok(Some(line)) => {
-                                if line.starts_with(';') {
-                                    callback(GameEvent::IpcLine {
-                                        value: &line[1..],
-                                    });
-                                    continue;
-                                }
                                 let mut log_data = data.log_data.lock().await;
-                                let entry = Entry {
-                                    time: OffsetDateTime::now_utc(),
-                                    level: log::Level::Error,
-                                    class: "STDERR",
-                                    message: line,
+                                let (entry, new_class) = match log_data.parse_entry(&line) {
+                                    Some(parsed) => parsed,
+                                    None => log_data.new_entry(log::Level::Info, "UNK", line),
                                 };
+                                if new_class {
+                                    events.publish(&GameEvent::AddLogClass{ value: &entry.class })
+                                }
                                 let (row_id, entry) = log_data.add_entry(entry);
-                                callback(GameEvent::Log ((
+                                events.publish(&GameEvent::Log ((
                                     row_id,
                                     entry,
                                 ).into()));
@@ -201,42 +540,168 @@ impl GameProcess {
                        break;
                     }
                     Some(cmd) = rx.recv() => {
-                        let r = stdin.write_all(cmd.as_bytes()).await.context("failed to write to stdin");
-                        if let Err(err) = r {
+                        if let Err(err) = write_command(&mut stdin, &cmd).await {
                             io_err = Some(err);
                             break;
                         }
-                        let _ = stdin.write_u8(b'\n').await;
-                        let r = stdin.flush().await.context("failed to flush stdin");
-                        if let Err(err) = r {
-                            io_err = Some(err);
-                            break;
+                    }
+                    _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                        deadline = None;
+                        match stage {
+                            ExitStage::Ipc => {
+                                info!("Emulator did not exit after STOP, terminating it");
+                                stage = terminate(&mut c);
+                                if stage == ExitStage::Terminate {
+                                    deadline = Some(Instant::now() + term_deadline);
+                                }
+                            }
+                            ExitStage::Terminate => {
+                                info!("Emulator did not exit after SIGTERM, killing it");
+                                stage = ExitStage::Kill;
+                                let _ = c.start_kill();
+                            }
+                            ExitStage::Exited | ExitStage::Kill => {}
                         }
                     }
                     Some(inner_cmd) = inner_rx.recv() => {
                         match inner_cmd {
                             InnerCommand::Kill => break,
+                            // Already stopping
+                            InnerCommand::Stop { .. } if stage != ExitStage::Exited => {}
+                            InnerCommand::Stop { ipc_timeout, term_timeout } => {
+                                term_deadline = term_timeout;
+                                if ipc_enabled && write_command(&mut stdin, &ipc::Command::Stop.encode()).await.is_ok() {
+                                    stage = ExitStage::Ipc;
+                                    deadline = Some(Instant::now() + ipc_timeout);
+                                } else {
+                                    stage = terminate(&mut c);
+                                    if stage == ExitStage::Terminate {
+                                        deadline = Some(Instant::now() + term_timeout);
+                                    }
+                                }
+                            }
                         };
                     }
                 }
             }
 
             if let Some(err) = io_err {
-                callback(GameEvent::IOError {
+                events.publish(&GameEvent::IOError {
                     err: err.to_string(),
                 })
             }
 
-            c.start_kill().expect("could not kill a child process");
-            let status = c
-                .wait()
-                .await
-                .expect("could not read the exit status code")
-                .code()
-                .unwrap_or(-1);
-            callback(GameEvent::GameExit { status });
+            if matches!(c.try_wait(), Ok(None)) {
+                stage = ExitStage::Kill;
+                if let Err(err) = c.start_kill() {
+                    events.publish(&GameEvent::IOError {
+                        err: format!("could not kill the emulator: {}", err),
+                    });
+                }
+            }
+            let exit = match c.wait().await {
+                Ok(exit_status) => ExitInfo::new(exit_status, stage),
+                Err(err) => {
+                    events.publish(&GameEvent::IOError {
+                        err: format!("could not read the exit status: {}", err),
+                    });
+                    ExitInfo {
+                        status: None,
+                        signal: None,
+                        stage,
+                    }
+                }
+            };
+            info!("Emulator exited: {} stage={:?}", exit, exit.stage);
+            status.lock().await.state = SessionState::Exited(exit.clone());
+            if let Some(session) = session
+                && let Err(e) = session.end(exit.code()).await
+            {
+                error!("could not record play session: {}", e);
+            }
+            events.close_with(&GameEvent::GameExit(exit));
+
+            // Nobody is left to delete the session
+            if events.subscription_count() == 0 {
+                info!("Removing session {}, no window is subscribed", id);
+                let state = app_handle.state::<GameBridgeStateType>();
+                state.lock().await.process_list.remove(&id);
+            }
         });
 
         (tx, inner_tx)
     }
 }
+
+/// Sends the classes, then the log rows in `range`. The log is only locked to read a chunk,
+/// from a blocking thread since spilled rows are read back from disk
+async fn replay_rows(
+    log_data: Arc<Mutex<LogData>>,
+    channel: Channel,
+    classes: Vec<InvokeResponseBody>,
+    range: Range<RowId>,
+) -> tauri::Result<()> {
+    for body in classes {
+        channel.send(body)?;
+    }
+    let mut from = range.start;
+    while from < range.end {
+        let log_data = log_data.clone();
+        let chunk = tokio::task::spawn_blocking(move || {
+            let log_data = log_data.blocking_lock();
+            let mut chunk = Vec::new();
+            for row in log_data.rows(from)? {
+                let (row_id, entry) = row?;
+                if row_id >= range.end || chunk.len() == REPLAY_CHUNK_ROWS {
+                    break;
+                }
+                chunk.push((row_id, GameEvent::Log((row_id, &*entry).into()).body()?));
+            }
+            anyhow::Ok(chunk)
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r);
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                error!("could not read the log to replay: {:#}", e);
+                return Ok(());
+            }
+        };
+        let Some((last, _)) = chunk.last() else {
+            break;
+        };
+        from = last + 1;
+        for (_, body) in chunk {
+            channel.send(body)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sends SIGTERM. Other platforms have no equivalent, so the emulator is killed right away
+fn terminate(c: &mut Child) -> ExitStage {
+    #[cfg(unix)]
+    if let Some(pid) = c.id() {
+        // SAFETY: kill has no memory safety requirements, the pid belongs to our child
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } == 0 {
+            return ExitStage::Terminate;
+        }
+        error!(
+            "could not send SIGTERM: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    let _ = c.start_kill();
+    ExitStage::Kill
+}
+
+async fn write_command(stdin: &mut ChildStdin, cmd: &str) -> anyhow::Result<()> {
+    stdin
+        .write_all(cmd.as_bytes())
+        .await
+        .context("failed to write to stdin")?;
+    let _ = stdin.write_u8(b'\n').await;
+    stdin.flush().await.context("failed to flush stdin")
+}
@@ -0,0 +1,178 @@
+use crate::game_process::game_process::GameEvent;
+use ::log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tauri::ipc::{Channel, InvokeResponseBody, IpcResponse};
+use tokio::sync::broadcast::{self, Receiver, error::RecvError};
+
+/// Events kept for subscribers that fall behind. Slower ones get a `Lagged` event and
+/// can read the missed rows with `game_process_get_log`
+const CAPACITY: usize = 1024;
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SubscriptionId(u64);
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+type Observer = Box<dyn Fn(&GameEvent) + Send + Sync>;
+
+/// The live events of a new subscription
+pub(super) enum Live {
+    Receiver(Receiver<InvokeResponseBody>),
+    /// The hub was closed before, with this last event
+    Closed(Option<InvokeResponseBody>),
+}
+
+/// Fans the events of a session out to every subscribed channel.
+/// Events are serialized once, then forwarded by a task per subscription
+pub struct EventHub {
+    /// Taken when the session ends, which lets the forwarding tasks finish
+    sender: Mutex<Option<broadcast::Sender<InvokeResponseBody>>>,
+    /// The event the hub was closed with, for subscriptions that come later
+    last: Mutex<Option<InvokeResponseBody>>,
+    /// Sees every event before it is sent, e.g. to follow the launch handshake
+    observer: Option<Observer>,
+    subscriptions: Arc<Mutex<HashMap<SubscriptionId, JoinHandle<()>>>>,
+    last_subscription: AtomicU64,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self {
+            sender: Mutex::new(Some(sender)),
+            last: Mutex::new(None),
+            observer: None,
+            subscriptions: Default::default(),
+            last_subscription: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_observer(mut self, observer: impl Fn(&GameEvent) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    pub fn publish(&self, ev: &GameEvent) {
+        if let Some(observer) = &self.observer {
+            observer(ev);
+        }
+        let sender = self.sender.lock().unwrap();
+        let Some(sender) = sender.as_ref().filter(|s| s.receiver_count() > 0) else {
+            return;
+        };
+        match ev.body() {
+            // Only fails when nobody is subscribed
+            Ok(body) => _ = sender.send(body),
+            Err(e) => error!("could not serialize game event: {}", e),
+        }
+    }
+
+    /// Publishes the last event, no more will be published. Subscribers get what is left
+    /// and are dropped, later ones only get this event
+    pub fn close_with(&self, ev: &GameEvent) {
+        if let Some(observer) = &self.observer {
+            observer(ev);
+        }
+        let body = ev
+            .body()
+            .inspect_err(|e| error!("could not serialize game event: {}", e))
+            .ok();
+        // Under the sender lock, so a subscription either gets it live or from `last`
+        let mut sender = self.sender.lock().unwrap();
+        if let Some(sender) = sender.take()
+            && let Some(body) = &body
+        {
+            _ = sender.send(body.clone());
+        }
+        *self.last.lock().unwrap() = body;
+    }
+
+    /// Subscribes the channel to the events published from now on
+    pub fn attach(&self, channel: Channel) -> SubscriptionId {
+        self.forward(channel, async { Ok(()) }, self.live())
+    }
+
+    /// Must be taken before reading the replay, so no event is missed in between
+    pub(super) fn live(&self) -> Live {
+        let sender = self.sender.lock().unwrap();
+        match sender.as_ref() {
+            Some(sender) => Live::Receiver(sender.subscribe()),
+            None => Live::Closed(self.last.lock().unwrap().clone()),
+        }
+    }
+
+    /// Runs `replay`, which sends the past events to the channel, then forwards the `live`
+    /// events until the hub or the channel closes
+    pub(super) fn forward(
+        &self,
+        channel: Channel,
+        replay: impl Future<Output = tauri::Result<()>> + Send + 'static,
+        live: Live,
+    ) -> SubscriptionId {
+        let id = SubscriptionId(self.last_subscription.fetch_add(1, Ordering::Relaxed) + 1);
+
+        // Held until the task is listed, so a closed channel can't remove it first
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let list = self.subscriptions.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            let result = match replay.await {
+                Ok(()) => send_live(&channel, live).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                debug!("subscription {} closed: {}", id, e);
+            }
+            list.lock().unwrap().remove(&id);
+        });
+        subscriptions.insert(id, handle);
+        id
+    }
+
+    /// Returns false if the subscription had already ended
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        match self.subscriptions.lock().unwrap().remove(&id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.lock().unwrap().len()
+    }
+}
+
+async fn send_live(channel: &Channel, live: Live) -> tauri::Result<()> {
+    let mut receiver = match live {
+        Live::Receiver(receiver) => receiver,
+        Live::Closed(last) => {
+            if let Some(body) = last {
+                channel.send(body)?;
+            }
+            return Ok(());
+        }
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(body) => channel.send(body)?,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("game event subscriber lagged behind, skipped={}", skipped);
+                channel.send(GameEvent::Lagged { skipped }.body()?)?;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Prefix of IPC lines in the emulator stderr. Commands are read from stdin, one per line
+pub const IPC_PREFIX: char = ';';
+
+const HANDSHAKE_START: &str = "#IPC_ENABLED";
+const HANDSHAKE_END: &str = "#IPC_END";
+
+/// Largest value a single memory patch can write
+const MAX_PATCH_BYTES: usize = 4096;
+
+/// Upper bound for the RESTART argument count, so a bad count can't swallow the following messages
+const MAX_RESTART_ARGS: usize = 256;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("unknown IPC message: {0}")]
+    UnknownMessage(String),
+    #[error("invalid RESTART argument count: {0}")]
+    InvalidArgCount(String),
+    #[error("invalid memory patch {field}: {value:?}")]
+    InvalidPatch { field: &'static str, value: String },
+    #[error("memory patch is too large: {0} bytes")]
+    PatchTooLarge(usize),
+    #[error("the emulator did not advertise {0}")]
+    MissingCapability(&'static str),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {
+    EnableMemoryPatch,
+    Unknown(String),
+}
+
+impl Capability {
+    fn parse(value: &str) -> Self {
+        match value {
+            "ENABLE_MEMORY_PATCH" => Self::EnableMemoryPatch,
+            _ => Self::Unknown(value.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::EnableMemoryPatch => "ENABLE_MEMORY_PATCH",
+            Self::Unknown(v) => v,
+        }
+    }
+}
+
+impl Serialize for Capability {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Messages sent by the emulator
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    /// The emulator speaks IPC and is sending its capabilities
+    Enabled,
+    /// Capabilities received. The emulator waits for [`Command::Run`]
+    Capabilities(Vec<Capability>),
+    /// The game asked to be started again with new arguments
+    Restart { args: Vec<String> },
+}
+
+/// Commands sent to the emulator
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Command {
+    Run,
+    Start,
+    Stop,
+    PatchMemory(MemoryPatch),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryPatch {
+    pub mod_name: String,
+    pub offset: String,
+    pub value: String,
+    #[serde(default)]
+    pub target: String,
+    #[serde(default)]
+    pub size: String,
+    pub is_offset: bool,
+    #[serde(default)]
+    pub little_endian: bool,
+    #[serde(default)]
+    pub patch_mask: u32,
+    #[serde(default)]
+    pub patch_size: u32,
+}
+
+impl MemoryPatch {
+    /// Checks the patch can be sent as a PATCH_MEMORY command without breaking the protocol
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |field, value: &str| Error::InvalidPatch {
+            field,
+            value: value.to_owned(),
+        };
+
+        if self.mod_name.is_empty() || self.mod_name.contains(['\n', '\r']) {
+            return Err(invalid("mod name", &self.mod_name));
+        }
+        if self.is_offset {
+            let offset = self.offset.trim();
+            let offset = offset.strip_prefix("0x").unwrap_or(offset);
+            if u64::from_str_radix(offset, 16).is_err() {
+                return Err(invalid("offset", &self.offset));
+            }
+        } else if !is_pattern(&self.offset) {
+            return Err(invalid("pattern", &self.offset));
+        }
+        let value_len = hex_len(&self.value).ok_or_else(|| invalid("value", &self.value))?;
+        if value_len == 0 {
+            return Err(invalid("value", &self.value));
+        }
+        if value_len.div_ceil(2) > MAX_PATCH_BYTES {
+            return Err(Error::PatchTooLarge(value_len.div_ceil(2)));
+        }
+        if !self.target.is_empty() && !is_pattern(&self.target) {
+            return Err(invalid("target", &self.target));
+        }
+        if !self.size.is_empty() {
+            let size = self.size.trim();
+            let size = match size.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => size.parse(),
+            }
+            .map_err(|_| invalid("size", &self.size))?;
+            if size > MAX_PATCH_BYTES {
+                return Err(Error::PatchTooLarge(size));
+            }
+        }
+        if self.patch_mask > 2 {
+            return Err(invalid("mask", &self.patch_mask.to_string()));
+        }
+        if self.patch_size as usize > MAX_PATCH_BYTES {
+            return Err(Error::PatchTooLarge(self.patch_size as usize));
+        }
+        Ok(())
+    }
+}
+
+/// Counts the hex digits of a value like `0x90 90`, or `None` if it isn't hex
+fn hex_len(value: &str) -> Option<usize> {
+    let value = value.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    let mut len = 0;
+    for c in value.chars().filter(|c| *c != ' ') {
+        if !c.is_ascii_hexdigit() {
+            return None;
+        }
+        len += 1;
+    }
+    Some(len)
+}
+
+/// Signature patterns are hex bytes separated by spaces, `?` and `??` are wildcards
+fn is_pattern(value: &str) -> bool {
+    let mut tokens = value.split_whitespace().peekable();
+    tokens.peek().is_some()
+        && tokens.all(|t| t.len() <= 2 && t.chars().all(|c| c == '?' || c.is_ascii_hexdigit()))
+}
+
+impl Command {
+    /// The lines written to stdin, without the trailing new line
+    pub fn encode(&self) -> String {
+        match self {
+            Self::Run => "RUN".to_owned(),
+            Self::Start => "START".to_owned(),
+            Self::Stop => "STOP".to_owned(),
+            Self::PatchMemory(p) => [
+                "PATCH_MEMORY",
+                &p.mod_name,
+                &p.offset,
+                &p.value,
+                &p.target,
+                &p.size,
+                if p.is_offset { "1" } else { "0" },
+                if p.little_endian { "1" } else { "0" },
+                &p.patch_mask.to_string(),
+                &p.patch_size.to_string(),
+            ]
+            .join("\n"),
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Capabilities(Vec<Capability>),
+    RestartCount,
+    RestartArgs {
+        remaining: usize,
+        args: Vec<String>,
+    },
+}
+
+/// Turns the IPC lines into [`Message`]s. Lines are fed without the [`IPC_PREFIX`]
+#[derive(Default)]
+pub struct Parser {
+    state: State,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, line: &str) -> Result<Option<Message>, Error> {
+        match std::mem::take(&mut self.state) {
+            State::Idle => match line {
+                HANDSHAKE_START => {
+                    self.state = State::Capabilities(Vec::new());
+                    Ok(Some(Message::Enabled))
+                }
+                "RESTART" => {
+                    self.state = State::RestartCount;
+                    Ok(None)
+                }
+                _ => Err(Error::UnknownMessage(line.to_owned())),
+            },
+            State::Capabilities(capabilities) if line == HANDSHAKE_END => {
+                Ok(Some(Message::Capabilities(capabilities)))
+            }
+            State::Capabilities(mut capabilities) => {
+                capabilities.push(Capability::parse(line));
+                self.state = State::Capabilities(capabilities);
+                Ok(None)
+            }
+            State::RestartCount => {
+                let remaining = line
+                    .parse()
+                    .ok()
+                    .filter(|n| *n <= MAX_RESTART_ARGS)
+                    .ok_or_else(|| Error::InvalidArgCount(line.to_owned()))?;
+                self.push_restart_arg(remaining, Vec::with_capacity(remaining))
+            }
+            State::RestartArgs {
+                remaining,
+                mut args,
+            } => {
+                args.push(line.to_owned());
+                self.push_restart_arg(remaining - 1, args)
+            }
+        }
+    }
+
+    fn push_restart_arg(
+        &mut self,
+        remaining: usize,
+        args: Vec<String>,
+    ) -> Result<Option<Message>, Error> {
+        if remaining == 0 {
+            return Ok(Some(Message::Restart { args }));
+        }
+        self.state = State::RestartArgs { remaining, args };
+        Ok(None)
+    }
+}
@@ -1,8 +1,33 @@
+use crate::game_process::session_log::SessionLog;
+use ::log::error;
+use anyhow::Context;
+use flate2::Compression;
+use flate2::write::GzEncoder;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
-use std::collections::{btree_map, BTreeMap, HashMap};
+use std::borrow::Cow;
+use std::collections::{vec_deque, HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::Rev;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
 use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+
+/// A spilled row is indexed every this many rows, to seek close to a row id
+const SPILL_INDEX_STEP: RowId = 1024;
+
+/// Rows read by a single page, so the log is not held while a filter skips most of them
+pub const MAX_PAGE_SCANNED_ROWS: usize = 20_000;
+
+/// Longer names come from lines that only look like log entries
+const MAX_CLASS_LEN: usize = 64;
+
+const LINE_TIME_FORMAT: &[time::format_description::BorrowedFormatItem] =
+    format_description!("[hour]:[minute]:[second]");
 
 #[derive(Copy, Clone, Default, Hash, Eq, PartialEq, Debug, Serialize, Deserialize, strum::AsRefStr)]
 #[serde(rename_all = "camelCase")]
@@ -19,13 +44,42 @@ pub enum Level {
 
 pub type RowId = u32;
 
-#[derive(Serialize, Clone)]
+/// How the emulator printed a row, only kept when it can't be rebuilt from the entry
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub enum RawLine {
+    /// `[class] <level> message`
+    #[default]
+    Formatted,
+    /// The message is the whole line
+    Message,
+    Original(Box<str>),
+}
+
+#[derive(Clone)]
 pub struct Entry {
     pub time: OffsetDateTime,
     pub level: Level,
-    pub class: &'static str,
+    /// Shared with the class table of the [`LogData`]
+    pub class: Arc<str>,
     pub message: String,
+    pub raw: RawLine,
+}
+
+impl Entry {
+    /// The line as the emulator printed it
+    pub fn raw_line(&self) -> Cow<'_, str> {
+        match &self.raw {
+            RawLine::Formatted => Cow::Owned(format!(
+                "[{}] <{}> {}",
+                self.class,
+                self.level.as_ref(),
+                self.message
+            )),
+            RawLine::Message => Cow::Borrowed(&self.message),
+            RawLine::Original(line) => Cow::Borrowed(line),
+        }
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -35,49 +89,438 @@ pub struct LogEntry<'a> {
     #[serde(with = "time::serde::timestamp")]
     pub time: OffsetDateTime,
     pub level: Level,
-    pub class: &'a str,
-    pub message: &'a str,
+    pub class: Cow<'a, str>,
+    pub message: Cow<'a, str>,
+}
+
+impl LogEntry<'_> {
+    pub fn into_owned(self) -> LogEntry<'static> {
+        LogEntry {
+            row_id: self.row_id,
+            time: self.time,
+            level: self.level,
+            class: Cow::Owned(self.class.into_owned()),
+            message: Cow::Owned(self.message.into_owned()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    #[default]
+    Forward,
+    Backward,
+}
+
+/// A window of the log, in ascending order
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogPage {
+    pub rows: Vec<LogEntry<'static>>,
+    /// Where the next page in the same direction starts, `None` at the end of the log.
+    /// The page may have less than `limit` rows when [`MAX_PAGE_SCANNED_ROWS`] rows were read
+    pub next_row_id: Option<RowId>,
+}
+
+/// Returned by `game_process_get_log_classes`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogClass {
+    pub name: String,
+    pub rows: usize,
+}
+
+/// How many rows are kept in memory, older ones are spilled to disk
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLimits {
+    pub max_rows: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for LogLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: Some(100_000),
+            max_bytes: Some(64 * 1024 * 1024),
+        }
+    }
+}
+
+/// Formats of `game_process_save_log`
+#[derive(Copy, Clone, Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// `HH:MM:SS [class] <level> message`
+    #[default]
+    Text,
+    /// The lines as the emulator printed them
+    Raw,
+    /// One JSON object per row, with the full timestamp
+    JsonLines,
+    Csv,
+}
+
+#[derive(Clone, Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Raw lines keep their ANSI color codes
+    pub keep_ansi: bool,
+    pub gzip: bool,
+}
+
+impl ExportOptions {
+    pub fn extension(&self) -> &'static str {
+        match (self.format, self.gzip) {
+            (ExportFormat::Text, false) => "txt",
+            (ExportFormat::Text, true) => "txt.gz",
+            (ExportFormat::Raw, false) => "log",
+            (ExportFormat::Raw, true) => "log.gz",
+            (ExportFormat::JsonLines, false) => "jsonl",
+            (ExportFormat::JsonLines, true) => "jsonl.gz",
+            (ExportFormat::Csv, false) => "csv",
+            (ExportFormat::Csv, true) => "csv.gz",
+        }
+    }
+}
+
+/// Writes the rows of a [`LogData`] accepted by the level and class filters, in one of the
+/// export formats. Empty filters accept every row
+#[derive(Clone, Default, Debug)]
+pub struct LogExporter {
+    options: ExportOptions,
+    levels: Vec<Level>,
+    classes: Vec<String>,
+}
+
+impl LogExporter {
+    pub fn new(options: ExportOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn levels(mut self, levels: Vec<Level>) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    pub fn classes(mut self, classes: Vec<String>) -> Self {
+        self.classes = classes;
+        self
+    }
+
+    pub fn options(&self) -> &ExportOptions {
+        &self.options
+    }
+
+    pub fn accepts(&self, entry: &Entry) -> bool {
+        (self.levels.is_empty() || self.levels.contains(&entry.level))
+            && (self.classes.is_empty() || self.classes.iter().any(|c| **c == *entry.class))
+    }
+
+    /// Compressed when `gzip` is set. Returns the number of rows written
+    pub fn export(&self, log: &LogData, w: impl Write) -> anyhow::Result<usize> {
+        if self.options.gzip {
+            let mut w = GzEncoder::new(w, Compression::default());
+            let rows = self.write_rows(log, &mut w)?;
+            w.finish()?.flush()?;
+            Ok(rows)
+        } else {
+            let mut w = w;
+            let rows = self.write_rows(log, &mut w)?;
+            w.flush()?;
+            Ok(rows)
+        }
+    }
+
+    fn write_rows(&self, log: &LogData, w: &mut impl Write) -> anyhow::Result<usize> {
+        self.write_header(w)?;
+        let mut count = 0;
+        for row in log.rows(0)? {
+            let (row_id, entry) = row?;
+            if self.accepts(&entry) {
+                self.write_row(w, row_id, &entry)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Written once before the rows
+    fn write_header(&self, w: &mut impl Write) -> io::Result<()> {
+        match self.options.format {
+            ExportFormat::Csv => w.write_all(b"row_id,time,level,class,message\n"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn write_row(&self, w: &mut impl Write, row_id: RowId, entry: &Entry) -> io::Result<()> {
+        match self.options.format {
+            ExportFormat::Text => writeln!(
+                w,
+                "{} [{}] <{}> {}",
+                entry
+                    .time
+                    .format(LINE_TIME_FORMAT)
+                    .map_err(io::Error::other)?,
+                entry.class,
+                entry.level.as_ref(),
+                entry.message
+            ),
+            ExportFormat::Raw => {
+                let line = entry.raw_line();
+                if self.options.keep_ansi {
+                    writeln!(w, "{}", line)
+                } else {
+                    writeln!(w, "{}", ANSI_REGEX.with(|rx| rx.replace_all(&line, "")))
+                }
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut *w, &ExportedRow::new(row_id, entry)?)?;
+                w.write_all(b"\n")
+            }
+            ExportFormat::Csv => {
+                let row = ExportedRow::new(row_id, entry)?;
+                writeln!(
+                    w,
+                    "{},{},{},{},{}",
+                    row.row_id,
+                    row.time,
+                    row.level.as_ref(),
+                    csv_field(row.class),
+                    csv_field(row.message)
+                )
+            }
+        }
+    }
+}
+
+/// A row of the JSON Lines and CSV exports
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedRow<'a> {
+    row_id: RowId,
+    /// RFC 3339, with the date and nanoseconds
+    time: String,
+    level: Level,
+    class: &'a str,
+    message: &'a str,
+}
+
+impl<'a> ExportedRow<'a> {
+    fn new(row_id: RowId, entry: &'a Entry) -> io::Result<Self> {
+        Ok(Self {
+            row_id,
+            time: entry.time.format(&Rfc3339).map_err(io::Error::other)?,
+            level: entry.level,
+            class: &entry.class,
+            message: &entry.message,
+        })
+    }
+}
+
+/// A row as written to the spill file, one JSON object per line
+#[derive(Serialize, Deserialize)]
+struct SpilledRow<'a> {
+    row_id: RowId,
+    #[serde(with = "time::serde::timestamp::nanoseconds")]
+    time: OffsetDateTime,
+    level: Level,
+    class: Cow<'a, str>,
+    message: Cow<'a, str>,
+    #[serde(default)]
+    raw: Cow<'a, RawLine>,
+}
+
+/// The spilled rows are written by a thread, so adding rows never waits on the disk
+struct SpillFile {
+    path: PathBuf,
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+    progress: Arc<SpillProgress>,
+    /// Every [`SPILL_INDEX_STEP`]th row id and its offset in the file
+    index: Vec<(RowId, u64)>,
+    /// Bytes sent to the writer, some may not be in the file yet
+    len: u64,
+}
+
+/// Bytes flushed to the spill file, and the error that stopped the writer
+struct SpillProgress {
+    written: Mutex<Written>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct Written {
+    /// Always a whole number of batches, the rows of a batch that failed can't be read
+    bytes: u64,
+    error: Option<String>,
+}
+
+impl SpillProgress {
+    fn update(&self, f: impl FnOnce(&mut Written)) {
+        f(&mut self.written.lock().unwrap());
+        self.cond.notify_all();
+    }
+}
+
+impl SpillFile {
+    fn create(path: PathBuf) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::create(&path).context("could not create the spill file")?;
+
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        let progress = Arc::new(SpillProgress {
+            written: Mutex::new(Written::default()),
+            cond: Condvar::new(),
+        });
+        let writer = thread::Builder::new()
+            .name("log-spill".to_owned())
+            .spawn({
+                let progress = progress.clone();
+                move || {
+                    let mut writer = BufWriter::new(file);
+                    for batch in receiver {
+                        // Readers open the file on their own
+                        if let Err(e) = writer.write_all(&batch).and_then(|_| writer.flush()) {
+                            error!("could not write the log spill file: {}", e);
+                            progress.update(|w| w.error = Some(e.to_string()));
+                            return;
+                        }
+                        progress.update(|w| w.bytes += batch.len() as u64);
+                    }
+                }
+            })
+            .context("could not start the spill writer")?;
+
+        Ok(Self {
+            path,
+            sender: Some(sender),
+            writer: Some(writer),
+            progress,
+            index: Vec::new(),
+            len: 0,
+        })
+    }
+
+    fn send(&mut self, batch: Vec<u8>) -> anyhow::Result<()> {
+        if let Some(e) = &self.progress.written.lock().unwrap().error {
+            anyhow::bail!("the spill writer stopped: {}", e);
+        }
+        self.len += batch.len() as u64;
+        self.sender
+            .as_ref()
+            .context("the spill writer stopped")?
+            .send(batch)
+            .context("the spill writer stopped")
+    }
+
+    /// Waits for the first `len` bytes to be in the file. Returns how many of them can be read,
+    /// fewer if the writer stopped before
+    fn wait_written(&self, len: u64) -> u64 {
+        let mut written = self.progress.written.lock().unwrap();
+        loop {
+            if written.bytes >= len {
+                return len;
+            }
+            if written.error.is_some() {
+                return written.bytes;
+            }
+            written = self.progress.cond.wait(written).unwrap();
+        }
+    }
+
+    fn open(&self, offset: u64) -> anyhow::Result<File> {
+        let mut file = File::open(&self.path).context("could not open the log spill file")?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(file)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        // The writer stops once the queued batches are written
+        drop(self.sender.take());
+        if let Some(writer) = self.writer.take()
+            && writer.join().is_err()
+        {
+            error!("the log spill writer panicked");
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("could not remove the log spill file: {}", e);
+        }
+    }
 }
 
 pub struct LogData {
-    pub rows: BTreeMap<RowId, Entry>,
+    /// The most recent rows, older ones are in the spill file
+    rows: VecDeque<(RowId, Entry)>,
+    rows_bytes: usize,
+    limits: LogLimits,
+    spill_path: PathBuf,
+    spill: Option<SpillFile>,
+    /// Rows that could not be spilled are dropped
+    spill_failed: bool,
     last_id: RowId,
-    class_cache: RefCell<HashMap<String, &'static str>>, // This contains leaked data
+    /// Class names in the order they were seen, with their row count
+    classes: Vec<(Arc<str>, usize)>,
+    class_index: HashMap<Arc<str>, usize>,
+    /// Every row is also written there
+    session_log: Option<SessionLog>,
 }
 
 thread_local! {
-    static ENTRY_REGEX: Regex = Regex::new(r"^(?i:\x1b\[0m)?(?i:\x1b\[[\d;]*m)?\[(.*?)]\s?<(.*?)>\s?(.*)(?i:\x1b\[0m)?$").unwrap()
+    static ENTRY_REGEX: Regex = Regex::new(r"^(?i:\x1b\[0m)?(?i:\x1b\[[\d;]*m)?\[(.*?)]\s?<(.*?)>\s?(.*?)(?i:\x1b\[0m)?$").unwrap();
+    static ANSI_REGEX: Regex = Regex::new(r"\x1b\[[\d;?]*[A-Za-z]").unwrap();
 }
 
 impl LogData {
-    pub fn new() -> Self {
+    /// `spill_path` is only created once the rows go over the limits
+    pub fn new(limits: LogLimits, spill_path: PathBuf) -> Self {
         Self {
-            rows: BTreeMap::new(),
+            rows: VecDeque::new(),
+            rows_bytes: 0,
+            limits,
+            spill_path,
+            spill: None,
+            spill_failed: false,
             last_id: 0,
-            class_cache: RefCell::new(HashMap::new()),
+            classes: Vec::new(),
+            class_index: HashMap::new(),
+            session_log: None,
         }
     }
 
+    pub fn set_session_log(&mut self, session_log: SessionLog) {
+        self.session_log = Some(session_log);
+    }
+
+    pub fn session_log(&self) -> Option<&SessionLog> {
+        self.session_log.as_ref()
+    }
+
+    /// Closes the session log once the queued rows are written, later rows are not logged
+    pub fn stop_session_log(&mut self) {
+        self.session_log = None;
+    }
+
     /// returns: Option<(Entry, bool)> If a line is parsed successfully,
     /// it returns the LogEntry and a boolean indicating if this is a new class
-    pub fn parse_entry(&self, line: &str) -> Option<(Entry, bool)> {
+    pub fn parse_entry(&mut self, line: &str) -> Option<(Entry, bool)> {
         let cap = ENTRY_REGEX.with(|rx| rx.captures(line))?;
 
-        let mut new_class = false;
-        let class_raw = cap.get(1)?.as_str();
-        let class = {
-            let mut class_cache = self.class_cache.borrow_mut();
-            if let Some(existing) = class_cache.get(class_raw) {
-                *existing
-            } else {
-                let boxed: &'static str = Box::leak(class_raw.to_owned().into_boxed_str());
-                class_cache.insert(class_raw.to_owned(), boxed);
-                new_class = true;
-                boxed
-            }
-        };
-        let level = cap.get(2)?.as_str();
-        let level = match level {
+        let class = cap.get(1)?.as_str();
+        if !is_class_name(class) {
+            return None;
+        }
+        let level_name = cap.get(2)?.as_str();
+        let level = match level_name {
             "Trace" => Level::Trace,
             "Debug" => Level::Debug,
             "Info" => Level::Info,
@@ -87,30 +530,382 @@ impl LogData {
             _ => Level::Unknown,
         };
 
-        let line = cap.get(3)?.as_str().to_owned();
+        let message = cap.get(3)?;
+
+        // Colors and extra spaces are only kept for raw exports
+        let raw = if cap.get(1)?.start() == 1
+            && message.start() == class.len() + level_name.len() + 6
+            && message.end() == line.len()
+            && level.as_ref() == level_name
+        {
+            RawLine::Formatted
+        } else {
+            RawLine::Original(line.into())
+        };
+
+        Some(self.build_entry(level, class, message.as_str().to_owned(), raw))
+    }
+
+    /// Builds an entry with the current time, for a line that is only a message.
+    /// The boolean tells if this is a new class
+    pub fn new_entry(&mut self, level: Level, class: &str, message: String) -> (Entry, bool) {
+        self.build_entry(level, class, message, RawLine::Message)
+    }
+
+    fn build_entry(
+        &mut self,
+        level: Level,
+        class: &str,
+        message: String,
+        raw: RawLine,
+    ) -> (Entry, bool) {
+        let (class, new_class) = self.intern(class);
+        let entry = Entry {
+            time: OffsetDateTime::now_utc(),
+            class,
+            level,
+            message,
+            raw,
+        };
+        (entry, new_class)
+    }
+
+    fn intern(&mut self, class: &str) -> (Arc<str>, bool) {
+        if let Some(i) = self.class_index.get(class) {
+            return (self.classes[*i].0.clone(), false);
+        }
+        let class: Arc<str> = Arc::from(class);
+        self.class_index.insert(class.clone(), self.classes.len());
+        self.classes.push((class.clone(), 0));
+        (class, true)
+    }
 
-        Some((
-            Entry {
-                time: OffsetDateTime::now_utc(),
-                class,
-                level,
-                message: line,
-            },
-            new_class,
-        ))
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.classes.iter().map(|(name, _)| &**name)
     }
 
-    pub fn add_entry<'a>(&'a mut self, entry: Entry) -> (RowId, &'a Entry) {
+    /// The class table with the number of rows of each class, spilled ones included
+    pub fn class_counts(&self) -> Vec<LogClass> {
+        self.classes
+            .iter()
+            .map(|(name, rows)| LogClass {
+                name: name.to_string(),
+                rows: *rows,
+            })
+            .collect()
+    }
+
+    pub fn add_entry(&mut self, entry: Entry) -> (RowId, &Entry) {
         let row_id = self.last_id;
         self.last_id += 1;
-        let row_entry = self.rows.entry(row_id);
-        let r: &'a Entry = match row_entry {
-            btree_map::Entry::Vacant(e) => e.insert(entry),
-            btree_map::Entry::Occupied(_) => {
-                panic!("we shouldn't be replacing rows")
+        if let Some(i) = self.class_index.get(&entry.class) {
+            self.classes[*i].1 += 1;
+        }
+        if let Some(session_log) = &self.session_log
+            && let Err(e) = session_log.write(row_id, &entry)
+        {
+            error!("could not write the session log, it is stopped: {:#}", e);
+            self.session_log = None;
+        }
+        self.rows_bytes += entry_size(&entry);
+        self.rows.push_back((row_id, entry));
+        if self.over_limits(1.0) {
+            self.spill_rows();
+        }
+        let (_, r) = self.rows.back().expect("the new row is never spilled");
+        (row_id, r)
+    }
+
+    /// Id of the next row to be added
+    pub fn next_row_id(&self) -> RowId {
+        self.last_id
+    }
+
+    /// All the rows from `from`, reading the spilled ones back from disk
+    pub fn rows(&self, from: RowId) -> anyhow::Result<Rows<'_>> {
+        let first_in_memory = self.rows.front().map_or(self.last_id, |(id, _)| *id);
+        let mut spilled = None;
+        if let Some(spill) = &self.spill
+            && from < first_in_memory
+        {
+            let offset = spill
+                .index
+                .iter()
+                .rev()
+                .find(|(id, _)| *id <= from)
+                .map_or(0, |(_, offset)| *offset);
+            // Rows can't be added while this borrows the log, but the writer may still be busy
+            let readable = spill.wait_written(spill.len);
+            let file = spill.open(offset)?.take(readable.saturating_sub(offset));
+            spilled = Some(BufReader::new(file).lines());
+        }
+        let skip = from.saturating_sub(first_in_memory) as usize;
+        Ok(Rows {
+            log: self,
+            from,
+            spilled,
+            memory: self.rows.range(skip.min(self.rows.len())..),
+        })
+    }
+
+    /// All the rows up to `to` included, newest first
+    pub fn rows_rev(&self, to: RowId) -> RowsRev<'_> {
+        let first_in_memory = self.rows.front().map_or(self.last_id, |(id, _)| *id);
+        let in_memory = match to.checked_sub(first_in_memory) {
+            Some(n) => (n as usize + 1).min(self.rows.len()),
+            None => 0,
+        };
+        let last_spilled = to.min(first_in_memory.saturating_sub(1));
+        let chunk = self
+            .spill
+            .as_ref()
+            .and_then(|spill| spill.index.iter().rposition(|(id, _)| *id <= last_spilled));
+        RowsRev {
+            log: self,
+            to,
+            memory: self.rows.range(..in_memory).rev(),
+            spilled: Vec::new(),
+            chunk,
+        }
+    }
+
+    /// Up to `limit` rows accepted by `filter`, starting at `from` and going in `direction`.
+    /// At most [`MAX_PAGE_SCANNED_ROWS`] rows are read
+    pub fn page(
+        &self,
+        from: Option<RowId>,
+        limit: usize,
+        direction: Direction,
+        filter: impl Fn(&Entry) -> bool,
+    ) -> anyhow::Result<LogPage> {
+        type RowIter<'a> = Box<dyn Iterator<Item = anyhow::Result<(RowId, Cow<'a, Entry>)>> + 'a>;
+        let rows: RowIter = match direction {
+            Direction::Forward => Box::new(self.rows(from.unwrap_or(0))?),
+            Direction::Backward => Box::new(self.rows_rev(from.unwrap_or(RowId::MAX))),
+        };
+
+        let mut page = Vec::new();
+        let mut next_row_id = None;
+        for (scanned, row) in rows.enumerate() {
+            let (row_id, entry) = row?;
+            if scanned == MAX_PAGE_SCANNED_ROWS {
+                next_row_id = Some(row_id);
+                break;
+            }
+            if !filter(&entry) {
+                continue;
             }
+            if page.len() == limit {
+                next_row_id = Some(row_id);
+                break;
+            }
+            page.push(LogEntry::from((row_id, &*entry)).into_owned());
+        }
+        if let Direction::Backward = direction {
+            page.reverse();
+        }
+        Ok(LogPage {
+            rows: page,
+            next_row_id,
+        })
+    }
+
+    /// Reads the spilled rows between the `chunk`th index entry and the next one
+    fn read_chunk(&self, chunk: usize) -> anyhow::Result<Vec<(RowId, Entry)>> {
+        let spill = self.spill.as_ref().context("the log was not spilled")?;
+        let start = spill.index[chunk].1;
+        let end = spill
+            .index
+            .get(chunk + 1)
+            .map_or(spill.len, |(_, offset)| *offset);
+        // The rows past a failed write are dropped
+        let end = spill.wait_written(end);
+        if end <= start {
+            return Ok(Vec::new());
+        }
+        let mut lines = String::new();
+        spill
+            .open(start)?
+            .take(end - start)
+            .read_to_string(&mut lines)?;
+        lines.lines().map(|line| self.parse_spilled(line)).collect()
+    }
+
+    fn parse_spilled(&self, line: &str) -> anyhow::Result<(RowId, Entry)> {
+        let row: SpilledRow = serde_json::from_str(line)?;
+        let entry = Entry {
+            time: row.time,
+            level: row.level,
+            class: self.known_class(&row.class),
+            message: row.message.into_owned(),
+            raw: row.raw.into_owned(),
         };
-        (row_id, r)
+        Ok((row.row_id, entry))
+    }
+
+    fn over_limits(&self, ratio: f64) -> bool {
+        let over = |max: Option<usize>, value: usize| {
+            max.is_some_and(|max| value as f64 > max as f64 * ratio)
+        };
+        // The last row always stays in memory
+        self.rows.len() > 1
+            && (over(self.limits.max_rows, self.rows.len())
+                || over(self.limits.max_bytes, self.rows_bytes))
+    }
+
+    /// Moves the oldest rows to disk until there is some headroom, so it doesn't happen on every row
+    fn spill_rows(&mut self) {
+        let mut spilled = Vec::new();
+        while self.over_limits(0.9) {
+            let Some(row) = self.rows.pop_front() else {
+                break;
+            };
+            self.rows_bytes -= entry_size(&row.1);
+            spilled.push(row);
+        }
+        if self.spill_failed {
+            return;
+        }
+        if let Err(e) = self.write_spilled(&spilled) {
+            error!(
+                "could not spill the log to disk, older rows are dropped: {:#}",
+                e
+            );
+            self.spill_failed = true;
+        }
+    }
+
+    fn write_spilled(&mut self, rows: &[(RowId, Entry)]) -> anyhow::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::create(self.spill_path.clone())?);
+        }
+        let spill = self.spill.as_mut().unwrap();
+        let mut batch = Vec::new();
+        for (row_id, entry) in rows {
+            if row_id % SPILL_INDEX_STEP == 0 || spill.index.is_empty() {
+                spill.index.push((*row_id, spill.len + batch.len() as u64));
+            }
+            serde_json::to_writer(
+                &mut batch,
+                &SpilledRow {
+                    row_id: *row_id,
+                    time: entry.time,
+                    level: entry.level,
+                    class: Cow::Borrowed(&entry.class),
+                    message: Cow::Borrowed(&entry.message),
+                    raw: Cow::Borrowed(&entry.raw),
+                },
+            )?;
+            batch.push(b'\n');
+        }
+        spill.send(batch)
+    }
+
+    /// Classes read back from disk were all interned when their rows were added
+    fn known_class(&self, class: &str) -> Arc<str> {
+        match self.class_index.get(class) {
+            Some(i) => self.classes[*i].0.clone(),
+            None => Arc::from(class),
+        }
+    }
+}
+
+fn is_class_name(class: &str) -> bool {
+    !class.is_empty()
+        && class.len() <= MAX_CLASS_LEN
+        && class
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'))
+}
+
+fn entry_size(entry: &Entry) -> usize {
+    let raw = match &entry.raw {
+        RawLine::Original(line) => line.len(),
+        _ => 0,
+    };
+    size_of::<(RowId, Entry)>() + entry.message.len() + raw
+}
+
+/// Quoted when it holds a separator, a quote or a line break
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Rows of a [`LogData`], the spilled ones first
+pub struct Rows<'a> {
+    log: &'a LogData,
+    from: RowId,
+    spilled: Option<io::Lines<BufReader<io::Take<File>>>>,
+    memory: vec_deque::Iter<'a, (RowId, Entry)>,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = anyhow::Result<(RowId, Cow<'a, Entry>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(lines) = &mut self.spilled {
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.spilled = None;
+                    break;
+                }
+            };
+            let (row_id, entry) = match self.log.parse_spilled(&line) {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e)),
+            };
+            if row_id < self.from {
+                continue;
+            }
+            return Some(Ok((row_id, Cow::Owned(entry))));
+        }
+        self.memory
+            .next()
+            .map(|(row_id, entry)| Ok((*row_id, Cow::Borrowed(entry))))
+    }
+}
+
+/// Rows of a [`LogData`] from the newest, the spilled ones are read back a chunk at a time
+pub struct RowsRev<'a> {
+    log: &'a LogData,
+    to: RowId,
+    memory: Rev<vec_deque::Iter<'a, (RowId, Entry)>>,
+    /// The chunk being read, in ascending order
+    spilled: Vec<(RowId, Entry)>,
+    /// Next chunk to read
+    chunk: Option<usize>,
+}
+
+impl<'a> Iterator for RowsRev<'a> {
+    type Item = anyhow::Result<(RowId, Cow<'a, Entry>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((row_id, entry)) = self.memory.next() {
+            return Some(Ok((*row_id, Cow::Borrowed(entry))));
+        }
+        loop {
+            if let Some((row_id, entry)) = self.spilled.pop() {
+                if row_id > self.to {
+                    continue;
+                }
+                return Some(Ok((row_id, Cow::Owned(entry))));
+            }
+            let chunk = self.chunk?;
+            self.chunk = chunk.checked_sub(1);
+            match self.log.read_chunk(chunk) {
+                Ok(rows) => self.spilled = rows,
+                Err(e) => {
+                    self.chunk = None;
+                    return Some(Err(e));
+                }
+            }
+        }
     }
 }
 
@@ -121,8 +916,8 @@ impl<'a> From<(RowId, &'a Entry)> for LogEntry<'a> {
             row_id: id,
             time: entry.time,
             level: entry.level,
-            class: entry.class,
-            message: &entry.message,
+            class: Cow::Borrowed(&entry.class),
+            message: Cow::Borrowed(&entry.message),
         }
     }
 }
@@ -0,0 +1,157 @@
+use crate::game_process::log::{Level, LogData, LogEntry, RowId};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Rows read by a single call, so the log is not held for the whole search
+pub const MAX_SCANNED_ROWS: usize = 20_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("invalid search pattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("could not read the log: {0:#}")]
+    Read(#[from] anyhow::Error),
+}
+
+#[derive(Copy, Clone, Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchMode {
+    #[default]
+    Substring,
+    Regex,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    /// Matched against the message. Empty matches every row
+    pub pattern: String,
+    pub mode: SearchMode,
+    pub case_insensitive: bool,
+    pub level: Vec<Level>,
+    pub log_class: Vec<String>,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub from_time: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub to_time: Option<OffsetDateTime>,
+    pub from_row_id: Option<RowId>,
+    /// Exclusive
+    pub to_row_id: Option<RowId>,
+    /// The `nextCursor` of the previous page
+    pub cursor: Option<RowId>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    #[serde(flatten)]
+    pub entry: LogEntry<'static>,
+    /// `[start, end)` of each hit in UTF-16 code units, as JS strings are indexed
+    pub ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub matches: Vec<SearchMatch>,
+    /// Where the next page starts, `None` when the search reached the end.
+    /// The page may have less than `limit` matches when [`MAX_SCANNED_ROWS`] rows were read
+    pub next_cursor: Option<RowId>,
+}
+
+impl SearchQuery {
+    fn matcher(&self) -> Result<Option<Regex>, Error> {
+        if self.pattern.is_empty() {
+            return Ok(None);
+        }
+        let pattern = match self.mode {
+            SearchMode::Substring => regex::escape(&self.pattern),
+            SearchMode::Regex => self.pattern.clone(),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()?;
+        Ok(Some(regex))
+    }
+}
+
+pub fn search(log: &LogData, query: &SearchQuery) -> Result<SearchResult, Error> {
+    let matcher = query.matcher()?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let from = query.cursor.max(query.from_row_id).unwrap_or(0);
+
+    let mut matches = Vec::new();
+    for (scanned, row) in log.rows(from)?.enumerate() {
+        let (row_id, entry) = row?;
+        if query.to_row_id.is_some_and(|to| row_id >= to) {
+            return Ok(SearchResult {
+                matches,
+                next_cursor: None,
+            });
+        }
+        if scanned == MAX_SCANNED_ROWS {
+            return Ok(SearchResult {
+                matches,
+                next_cursor: Some(row_id),
+            });
+        }
+        if !query.level.is_empty() && !query.level.contains(&entry.level) {
+            continue;
+        }
+        if !query.log_class.is_empty() && !query.log_class.iter().any(|c| **c == *entry.class) {
+            continue;
+        }
+        if query.from_time.is_some_and(|t| entry.time < t)
+            || query.to_time.is_some_and(|t| entry.time > t)
+        {
+            continue;
+        }
+        if let Some(regex) = &matcher
+            && !regex.is_match(&entry.message)
+        {
+            continue;
+        }
+
+        if matches.len() == limit {
+            return Ok(SearchResult {
+                matches,
+                next_cursor: Some(row_id),
+            });
+        }
+        matches.push(SearchMatch {
+            ranges: matcher
+                .as_ref()
+                .map_or_else(Vec::new, |regex| utf16_ranges(&entry.message, regex)),
+            entry: LogEntry::from((row_id, &*entry)).into_owned(),
+        });
+    }
+    Ok(SearchResult {
+        matches,
+        next_cursor: None,
+    })
+}
+
+fn utf16_ranges(message: &str, regex: &Regex) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    // Byte offsets are converted going forward, the matches are in order
+    let (mut byte_pos, mut utf16_pos) = (0, 0);
+    let mut to_utf16 = |byte: usize| {
+        utf16_pos += message[byte_pos..byte].encode_utf16().count();
+        byte_pos = byte;
+        utf16_pos
+    };
+    for m in regex.find_iter(message) {
+        if m.is_empty() {
+            continue;
+        }
+        let start = to_utf16(m.start());
+        let end = to_utf16(m.end());
+        ranges.push((start, end));
+    }
+    ranges
+}
@@ -0,0 +1,318 @@
+use crate::file_format::psf::PSF;
+use crate::file_format::psf::param_sfo::ParamSfo;
+use crate::game_process::log::{Entry, ExportOptions, LogExporter, RowId};
+use ::log::{debug, error, warn};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+use time::OffsetDateTime;
+use time::macros::format_description;
+
+/// Folder of the sessions whose game is not known
+pub const UNKNOWN_TITLE: &str = "UNKNOWN";
+
+const EXTENSION: &str = "log";
+
+/// How the logs of past sessions are kept, for each game
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLogPolicy {
+    pub enabled: bool,
+    /// The log goes on in a new file past this size
+    pub max_file_bytes: u64,
+    /// The oldest sessions are deleted past these
+    pub max_files: Option<usize>,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for SessionLogPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_bytes: 32 * 1024 * 1024,
+            max_files: Some(50),
+            max_total_bytes: Some(512 * 1024 * 1024),
+        }
+    }
+}
+
+/// Returned by `game_process_list_session_logs`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLogFile {
+    pub name: String,
+    pub size: u64,
+    #[serde(with = "time::serde::timestamp")]
+    pub modified: OffsetDateTime,
+}
+
+/// Copy of a session log, written as `<dir>/<timestamp>.log`, then `<timestamp>.1.log` and so on
+/// once a file is full. The files are written by a thread, so adding rows never waits on the disk
+pub struct SessionLog {
+    dir: PathBuf,
+    stem: String,
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+    exporter: LogExporter,
+}
+
+impl SessionLog {
+    /// `dir` is the folder of the game, see [`title_dir`]
+    pub fn create(dir: PathBuf, policy: SessionLogPolicy) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir).context("could not create the session log folder")?;
+
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let timestamp = now
+            .format(format_description!(
+                "[year]-[month]-[day]_[hour]-[minute]-[second]"
+            ))
+            .context("could not format the session start")?;
+
+        // Sessions of the same game may start within a second
+        let mut stem = timestamp.clone();
+        let mut n = 1;
+        let file = loop {
+            match create_new(&dir.join(format!("{}.{}", stem, EXTENSION))) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && n < 100 => {
+                    n += 1;
+                    stem = format!("{}_{}", timestamp, n);
+                }
+                r => break r.context("could not create the session log")?,
+            }
+        };
+
+        let mut parts = Parts {
+            dir: dir.clone(),
+            stem: stem.clone(),
+            part: 0,
+            writer: LineWriter::new(file),
+            len: 0,
+            policy,
+        };
+        parts.prune();
+
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        let writer = thread::Builder::new()
+            .name("session-log".to_owned())
+            .spawn(move || {
+                for line in receiver {
+                    if let Err(e) = parts.write(&line) {
+                        error!("could not write the session log: {:#}", e);
+                        return;
+                    }
+                }
+            })
+            .context("could not start the session log writer")?;
+
+        Ok(Self {
+            dir,
+            stem,
+            sender: Some(sender),
+            writer: Some(writer),
+            exporter: LogExporter::new(ExportOptions::default()),
+        })
+    }
+
+    /// Queues the row, each line is written as soon as the writer gets to it
+    pub fn write(&self, row_id: RowId, entry: &Entry) -> anyhow::Result<()> {
+        let mut line = Vec::new();
+        self.exporter.write_row(&mut line, row_id, entry)?;
+        self.sender
+            .as_ref()
+            .context("the session log writer stopped")?
+            .send(line)
+            .context("the session log writer stopped")
+    }
+
+    /// Whether `path` is one of the files of this session
+    pub fn owns(&self, path: &Path) -> bool {
+        path.parent() == Some(&*self.dir)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| session_stem(name) == self.stem)
+    }
+}
+
+impl Drop for SessionLog {
+    fn drop(&mut self) {
+        // The writer stops once the queued lines are written
+        drop(self.sender.take());
+        if let Some(writer) = self.writer.take()
+            && writer.join().is_err()
+        {
+            error!("the session log writer panicked");
+        }
+    }
+}
+
+/// The files of a [`SessionLog`], owned by its writer thread
+struct Parts {
+    dir: PathBuf,
+    stem: String,
+    part: u32,
+    /// Flushed after every line, so the file is complete even if the launcher crashes
+    writer: LineWriter<File>,
+    len: u64,
+    policy: SessionLogPolicy,
+}
+
+impl Parts {
+    fn path(&self) -> PathBuf {
+        match self.part {
+            0 => self.dir.join(format!("{}.{}", self.stem, EXTENSION)),
+            part => self
+                .dir
+                .join(format!("{}.{}.{}", self.stem, part, EXTENSION)),
+        }
+    }
+
+    fn write(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        let len = line.len() as u64;
+        if self.len > 0 && self.len + len > self.policy.max_file_bytes {
+            self.part += 1;
+            self.writer = LineWriter::new(
+                create_new(&self.path()).context("could not create the session log")?,
+            );
+            self.len = 0;
+            self.prune();
+        }
+        self.writer.write_all(line)?;
+        self.len += len;
+        Ok(())
+    }
+
+    /// Deletes the logs of the oldest sessions of the game until it is within the policy.
+    /// The parts of a session are kept or deleted together, the current one is never deleted
+    fn prune(&self) {
+        let files = match list(&self.dir) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("could not list the session logs: {:#}", e);
+                return;
+            }
+        };
+
+        // Newest first, like the files
+        let mut sessions: Vec<(&str, Vec<&SessionLogFile>)> = Vec::new();
+        for file in &files {
+            let stem = session_stem(&file.name);
+            match sessions.iter_mut().find(|(s, _)| *s == stem) {
+                Some((_, parts)) => parts.push(file),
+                None => sessions.push((stem, vec![file])),
+            }
+        }
+
+        let mut count = 1;
+        let mut total: u64 = sessions
+            .iter()
+            .filter(|(stem, _)| *stem == self.stem)
+            .flat_map(|(_, parts)| parts)
+            .map(|file| file.size)
+            .sum();
+        for (stem, parts) in sessions {
+            if stem == self.stem {
+                continue;
+            }
+            count += 1;
+            total += parts.iter().map(|file| file.size).sum::<u64>();
+            let over = self.policy.max_files.is_some_and(|max| count > max)
+                || self.policy.max_total_bytes.is_some_and(|max| total > max);
+            if !over {
+                continue;
+            }
+            for file in parts {
+                let path = self.dir.join(&file.name);
+                debug!("removing old session log: {}", path.display());
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("could not remove session log {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// `<stem>.log` and `<stem>.<part>.log` are parts of the same session, stems have no dot
+fn session_stem(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+fn create_new(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// The title id of the game in `game_path`, or its folder name if it has no param.sfo
+pub fn title_id(game_path: &Path) -> Option<String> {
+    let param_sfo = game_path.join("sce_sys").join("param.sfo");
+    if let Ok(psf) = PSF::open(&param_sfo)
+        && let Some(title_id) = ParamSfo::new(&psf).title_id()
+    {
+        return Some(title_id.to_owned());
+    }
+    game_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// The folder of a game under `root`. Characters that can't be in a folder name are replaced
+pub fn title_dir(root: &Path, title_id: &str) -> PathBuf {
+    let name: String = title_id
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => c,
+            _ => '_',
+        })
+        .collect();
+    if name.is_empty() {
+        root.join(UNKNOWN_TITLE)
+    } else {
+        root.join(name)
+    }
+}
+
+/// The logs in the folder of a game, newest first
+pub fn list(dir: &Path) -> anyhow::Result<Vec<SessionLogFile>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("could not read the session log folder"),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != EXTENSION) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        files.push(SessionLogFile {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH).into(),
+        });
+    }
+    files.sort_by(|a, b| b.modified.cmp(&a.modified).then(b.name.cmp(&a.name)));
+    Ok(files)
+}
+
+/// The path of a log listed by [`list`]. Names that would leave the folder are refused
+pub fn file_path(dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let is_file_name = Path::new(name).file_name().is_some_and(|n| n == name);
+    if !is_file_name
+        || Path::new(name)
+            .extension()
+            .is_none_or(|ext| ext != EXTENSION)
+    {
+        anyhow::bail!("invalid session log name: {}", name);
+    }
+    Ok(dir.join(name))
+}
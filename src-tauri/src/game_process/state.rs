@@ -1,17 +1,93 @@
 use crate::game_process::game_process::GameProcess;
+use crate::game_process::log::{LogData, LogLimits};
+use crate::game_process::session_log::{self, SessionLog, SessionLogPolicy, UNKNOWN_TITLE};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{fmt, fs};
 use tauri::async_runtime::Mutex;
 use tauri::{AppHandle, Manager};
 
+/// Launcher-assigned handle of a game process. Unlike the pid, it is never
+/// reused and stays the same when the emulator restarts
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionId(u64);
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 pub struct GameBridge {
-    pub(crate) process_list: HashMap<u32, GameProcess>,
+    pub(crate) process_list: HashMap<SessionId, GameProcess>,
+    last_session: u64,
+    /// Used by the sessions started from now on
+    pub(crate) log_limits: LogLimits,
+    /// Where the logs that don't fit in memory go
+    spill_dir: PathBuf,
+    /// Used by the sessions started from now on
+    pub(crate) session_log_policy: SessionLogPolicy,
+    /// Holds a folder of session logs for each game
+    session_log_dir: PathBuf,
 }
 
 impl GameBridge {
     pub fn register(app_handle: &AppHandle) {
+        let spill_dir = app_handle
+            .path()
+            .app_cache_dir()
+            .unwrap_or_else(|_| std::env::temp_dir())
+            .join("game-logs");
+        // Left behind if the launcher didn't exit cleanly
+        if spill_dir.exists()
+            && let Err(e) = fs::remove_dir_all(&spill_dir)
+        {
+            warn!("could not clean the log spill folder: {}", e);
+        }
+
+        let session_log_dir = app_handle
+            .path()
+            .app_log_dir()
+            .unwrap_or_else(|_| std::env::temp_dir())
+            .join("games");
+
         let state = GameBridge {
             process_list: Default::default(),
+            last_session: 0,
+            log_limits: LogLimits::default(),
+            spill_dir,
+            session_log_policy: SessionLogPolicy::default(),
+            session_log_dir,
         };
         app_handle.manage(Mutex::new(state));
     }
+
+    pub fn next_session_id(&mut self) -> SessionId {
+        self.last_session += 1;
+        SessionId(self.last_session)
+    }
+
+    /// Sessions without a `title_id` are logged under [`UNKNOWN_TITLE`]
+    pub(crate) fn new_log_data(&self, id: SessionId, title_id: Option<&str>) -> LogData {
+        let mut log_data = LogData::new(
+            self.log_limits,
+            self.spill_dir.join(format!("session-{}.jsonl", id)),
+        );
+        if self.session_log_policy.enabled {
+            let dir = self.title_log_dir(title_id.unwrap_or(UNKNOWN_TITLE));
+            match SessionLog::create(dir, self.session_log_policy) {
+                Ok(session_log) => log_data.set_session_log(session_log),
+                Err(e) => error!("could not create the session log: {:#}", e),
+            }
+        }
+        log_data
+    }
+
+    /// Where the session logs of a game are
+    pub(crate) fn title_log_dir(&self, title_id: &str) -> PathBuf {
+        session_log::title_dir(&self.session_log_dir, title_id)
+    }
 }
@@ -1,14 +1,42 @@
-use crate::{file_format, game_process, utility_commands};
+use crate::{file_format, game_process, launch, library, pkg_install, utility_commands};
 
 pub fn all_handlers() -> Box<dyn Fn(tauri::ipc::Invoke<tauri::Wry>) -> bool + Send + Sync> {
     Box::new(tauri::generate_handler![
+        file_format::pkg::js::read_pkg_entry,
+        file_format::pkg::js::read_pkg_info,
+        file_format::psf::js::read_param_sfo,
         file_format::psf::js::read_psf,
+        file_format::trp::js::read_trophies,
+        file_format::trp::js::read_trophy_icon,
         game_process::command::game_process_delete,
+        game_process::command::game_process_delete_session_log,
+        game_process::command::game_process_export_log,
         game_process::command::game_process_get_log,
+        game_process::command::game_process_get_log_classes,
         game_process::command::game_process_kill,
+        game_process::command::game_process_list,
+        game_process::command::game_process_list_session_logs,
+        game_process::command::game_process_open_session_log,
+        game_process::command::game_process_patch_memory,
         game_process::command::game_process_save_log,
-        game_process::command::game_process_send,
+        game_process::command::game_process_search_log,
+        game_process::command::game_process_send_ipc,
+        game_process::command::game_process_set_log_limits,
+        game_process::command::game_process_set_session_log_policy,
         game_process::command::game_process_spawn,
+        game_process::command::game_process_stop,
+        game_process::command::game_process_subscribe,
+        game_process::command::game_process_unsubscribe,
+        launch::game_launch,
+        library::command::library_add_root,
+        library::command::library_list_roots,
+        library::command::library_remove_root,
+        library::command::library_rescan_root,
+        library::command::library_scan,
+        library::command::library_unwatch,
+        library::command::library_watch,
+        pkg_install::pkg_install,
+        pkg_install::pkg_install_cancel,
         utility_commands::extract_zip,
         utility_commands::make_it_executable,
         utility_commands::open_path,
@@ -0,0 +1,345 @@
+use crate::db;
+use crate::game_process::GameBridgeStateType;
+use crate::game_process::game_process::{ExitInfo, GameEvent, GameProcess, ProcessData};
+use crate::game_process::hub::EventHub;
+use crate::game_process::ipc::{self, Capability, MemoryPatch};
+use crate::game_process::session_log;
+use crate::game_process::state::SessionId;
+use anyhow::Context;
+use anyhow_tauri::IntoTAResult;
+use log::{error, info, warn};
+use serde::Deserialize;
+use sqlx::Row;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use tokio::sync::oneshot;
+
+/// How long the emulator has to finish the IPC handshake or print its first log line
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stores written by the frontend with the patches and cheats enabled for each game
+const PATCHES_STORE: &str = "patches.json";
+const CHEATS_STORE: &str = "cheats.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("game {0} is not in the library")]
+    GameNotFound(i64),
+    #[error("emulator binary not found: {0}")]
+    EmulatorNotFound(PathBuf),
+    #[error("game binary (eboot.bin) not found: {0}")]
+    GameBinaryNotFound(PathBuf),
+    #[error("patch file not found: {0}")]
+    PatchFileNotFound(PathBuf),
+    #[error("invalid name in the patch or cheat settings: {0:?}")]
+    InvalidModName(String),
+    #[error("the emulator did not start in time")]
+    HandshakeTimeout,
+    #[error("the emulator exited while starting, {0}")]
+    ExitedEarly(ExitInfo),
+}
+
+/// How a game is started
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchProfile {
+    pub emulator: PathBuf,
+    /// Where the emulator `user/` folder lives. Defaults to the emulator folder
+    pub work_dir: Option<PathBuf>,
+    /// Replaces the default arguments (patch file and game binary)
+    pub args: Option<Vec<String>>,
+}
+
+struct Game {
+    path: PathBuf,
+    title_id: String,
+    version: String,
+}
+
+/// The parts of a cheat file used to build the memory patches
+#[derive(Deserialize)]
+struct CheatFile {
+    mods: Vec<CheatMod>,
+}
+
+#[derive(Deserialize)]
+struct CheatMod {
+    name: String,
+    hint: Option<bool>,
+    memory: Vec<CheatMemory>,
+}
+
+#[derive(Deserialize)]
+struct CheatMemory {
+    offset: String,
+    on: String,
+}
+
+enum Handshake {
+    Ipc(Vec<Capability>),
+    NoIpc,
+    Exited(ExitInfo),
+}
+
+async fn find_game(app_handle: &AppHandle, game_id: i64) -> anyhow::Result<Game> {
+    let pool = db::pool(app_handle).await?;
+    let row = sqlx::query("SELECT path, cusa, version FROM games WHERE id = $1")
+        .bind(game_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(Error::GameNotFound(game_id))?;
+    Ok(Game {
+        path: PathBuf::from(row.try_get::<String, _>("path")?),
+        title_id: row.try_get("cusa")?,
+        version: row.try_get("version")?,
+    })
+}
+
+/// Names from the settings become file names, they must not leave their folder
+fn checked_name(name: &str) -> Result<&str, Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(Error::InvalidModName(name.to_owned()));
+    }
+    Ok(name)
+}
+
+/// The patch file of the repository enabled for the game, if any
+fn find_patch_file(app_handle: &AppHandle, game: &Game) -> anyhow::Result<Option<PathBuf>> {
+    let store = app_handle.store(PATCHES_STORE)?;
+    let Some(repo) = store
+        .get("enabled_repo")
+        .and_then(|v| v.get(&game.title_id)?.as_str().map(str::to_owned))
+    else {
+        return Ok(None);
+    };
+    let Some(file) = store.get("store").and_then(|v| {
+        v.get(&repo)?
+            .get(&game.title_id)?
+            .as_str()
+            .map(str::to_owned)
+    }) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        app_handle
+            .path()
+            .app_data_dir()?
+            .join("patches")
+            .join(checked_name(&repo)?)
+            .join(checked_name(&file)?),
+    ))
+}
+
+/// The memory patches of the cheats enabled for the game version
+fn find_memory_patches(app_handle: &AppHandle, game: &Game) -> anyhow::Result<Vec<MemoryPatch>> {
+    let key = format!("{}_{}", game.title_id, game.version);
+    let store = app_handle.store(CHEATS_STORE)?;
+    let Some(enabled) = store.get("enabled").and_then(|v| v.get(&key).cloned()) else {
+        return Ok(Vec::new());
+    };
+    // Mod names by repository
+    let enabled: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(enabled).context("invalid enabled cheats")?;
+
+    let cheat_dir = app_handle.path().app_data_dir()?.join("cheats");
+    let mut patches = Vec::new();
+    for (repo, names) in enabled {
+        let path = cheat_dir
+            .join(checked_name(&repo)?)
+            .join(format!("{}.json", checked_name(&key)?));
+        if !path.exists() {
+            continue;
+        }
+        let file: CheatFile = serde_json::from_slice(&fs::read(&path)?)
+            .with_context(|| format!("invalid cheat file {}", path.display()))?;
+        for m in file.mods.into_iter().filter(|m| names.contains(&m.name)) {
+            for memory in m.memory {
+                patches.push(MemoryPatch {
+                    mod_name: m.name.clone(),
+                    offset: memory.offset,
+                    value: memory.on,
+                    target: String::new(),
+                    size: String::new(),
+                    // Hinted cheats search for their target instead of using an offset
+                    is_offset: !m.hint.unwrap_or(false),
+                    little_endian: false,
+                    patch_mask: 0,
+                    patch_size: 0,
+                });
+            }
+        }
+    }
+    Ok(patches)
+}
+
+fn build_args(
+    game_path: &Path,
+    profile: &LaunchProfile,
+    patch_file: Option<&Path>,
+) -> Result<Vec<String>, Error> {
+    let game_binary = game_path.join("eboot.bin");
+    if !game_binary.exists() {
+        return Err(Error::GameBinaryNotFound(game_binary));
+    }
+    if let Some(args) = &profile.args {
+        return Ok(args.clone());
+    }
+
+    let mut args = Vec::new();
+    if let Some(patch_file) = patch_file {
+        if !patch_file.exists() {
+            return Err(Error::PatchFileNotFound(patch_file.to_owned()));
+        }
+        args.push("-p".to_owned());
+        args.push(patch_file.to_string_lossy().into_owned());
+    }
+    args.push(game_binary.to_string_lossy().into_owned());
+    Ok(args)
+}
+
+/// Starts the game and drives it through the IPC handshake. The patch file and the
+/// memory patches are the ones enabled for the game, whoever starts it
+pub async fn launch(
+    app_handle: &AppHandle,
+    game_id: i64,
+    profile: LaunchProfile,
+    on_event: Channel,
+    data: Option<ProcessData>,
+) -> anyhow::Result<GameProcess> {
+    let game = find_game(app_handle, game_id).await?;
+    let game_path = game.path.clone();
+
+    if !profile.emulator.exists() {
+        return Err(Error::EmulatorNotFound(profile.emulator).into());
+    }
+    let patch_file = find_patch_file(app_handle, &game).context("could not find the patches")?;
+    let memory_patches =
+        find_memory_patches(app_handle, &game).context("could not read the cheats")?;
+    let args = build_args(&game_path, &profile, patch_file.as_deref())?;
+
+    let work_dir = match &profile.work_dir {
+        Some(dir) => dir.clone(),
+        None => profile
+            .emulator
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+    fs::create_dir_all(work_dir.join("user")).context("could not create the user folder")?;
+
+    let title_id = session_log::title_id(&game_path);
+
+    let (handshake_tx, handshake_rx) = oneshot::channel();
+    let handshake_tx = Mutex::new(Some(handshake_tx));
+    let ipc_enabled = AtomicBool::new(false);
+    let events = EventHub::new().with_observer(move |ev| {
+        let handshake = match ev {
+            GameEvent::IpcEnabled => {
+                ipc_enabled.store(true, Ordering::Relaxed);
+                None
+            }
+            GameEvent::Capabilities { capabilities } => Some(Handshake::Ipc(capabilities.to_vec())),
+            // Emulators without IPC start logging right away
+            GameEvent::AddLogClass { .. } if !ipc_enabled.load(Ordering::Relaxed) => {
+                Some(Handshake::NoIpc)
+            }
+            GameEvent::GameExit(exit) => Some(Handshake::Exited(exit.clone())),
+            _ => None,
+        };
+        if let Some(handshake) = handshake
+            && let Some(tx) = handshake_tx.lock().unwrap().take()
+        {
+            let _ = tx.send(handshake);
+        }
+    });
+    events.attach(on_event);
+
+    info!(
+        "Launching game {} with {}",
+        game_path.display(),
+        profile.emulator.display()
+    );
+    let process = GameProcess::start(
+        app_handle,
+        &profile.emulator,
+        &work_dir,
+        args,
+        events,
+        data,
+        title_id.as_deref(),
+        Some(&game_path),
+    )
+    .await?;
+
+    let handshake = match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake_rx).await {
+        Ok(Ok(handshake)) => handshake,
+        _ => {
+            let _ = process.kill().await;
+            let state = app_handle.state::<GameBridgeStateType>();
+            state.lock().await.process_list.remove(&process.id());
+            return Err(Error::HandshakeTimeout.into());
+        }
+    };
+
+    match handshake {
+        Handshake::Ipc(capabilities) => {
+            if capabilities.contains(&Capability::EnableMemoryPatch) {
+                for patch in &memory_patches {
+                    if let Err(e) = process
+                        .send_command(&ipc::Command::PatchMemory(patch.clone()))
+                        .await
+                    {
+                        warn!("could not apply memory patch {}: {}", patch.mod_name, e);
+                    }
+                }
+            }
+            process.send_command(&ipc::Command::Start).await?;
+            // Invalid patches are dropped so a restart doesn't send them again
+            process
+                .set_memory_patches(
+                    memory_patches
+                        .into_iter()
+                        .filter(|p| p.validate().is_ok())
+                        .collect(),
+                )
+                .await;
+        }
+        Handshake::NoIpc => {}
+        Handshake::Exited(exit) => return Err(Error::ExitedEarly(exit).into()),
+    }
+
+    Ok(process)
+}
+
+#[tauri::command]
+pub async fn game_launch(
+    app_handle: AppHandle,
+    game_id: i64,
+    profile: LaunchProfile,
+    on_event: Channel,
+    copy_data_from: Option<SessionId>,
+) -> anyhow_tauri::TAResult<SessionId> {
+    let mut data = None;
+    if let Some(old_session) = copy_data_from {
+        let state = app_handle.state::<GameBridgeStateType>();
+        let state = state.lock().await;
+        let Some(old_proc) = state.process_list.get(&old_session) else {
+            return Err(anyhow::anyhow!("old process not found")).into_ta_result();
+        };
+        data = Some(old_proc.data().clone());
+    }
+
+    let process = launch(&app_handle, game_id, profile, on_event, data)
+        .await
+        .inspect_err(|e| error!("could not launch the game: err={}", e))
+        .into_ta_result()?;
+
+    Ok(process.id())
+}
@@ -1,15 +1,20 @@
-#![feature(let_chains)]
-
 use crate::game_process::state::GameBridge;
-use log::info;
+use log::{error, info};
 use tauri::Manager;
+use crate::library::watcher::LibraryWatcher;
+use crate::library::stats::PlaySession;
 use crate::logger::build_log_plugin;
+use crate::pkg_install::PkgInstaller;
 
-mod file_format;
-mod game_process;
+pub mod file_format;
+pub mod game_process;
 mod handlers;
+mod launch;
+mod library;
 mod logger;
+mod pkg_install;
 mod utility_commands;
+mod db;
 mod db_migrations;
 
 pub fn run() {
@@ -32,7 +37,7 @@ pub fn run() {
         }))
         .plugin(
             tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:data.db", db_migrations::gen_migrations())
+                .add_migrations(db::DB_URL, db_migrations::gen_migrations())
                 .build()
         )
         .plugin(tauri_plugin_store::Builder::new().build())
@@ -45,7 +50,15 @@ pub fn run() {
             } else {
                 info!("Starting app. Unknown build git ref");
             }
-            GameBridge::register(&app.handle());
+            // Before any game is started, so only the sessions of the previous run are closed
+            match tauri::async_runtime::block_on(PlaySession::close_abandoned(app.handle())) {
+                Ok(0) => {}
+                Ok(n) => info!("Closed {} play sessions left open", n),
+                Err(e) => error!("could not close play sessions left open: {:#}", e),
+            }
+            GameBridge::register(app.handle());
+            LibraryWatcher::register(app.handle());
+            PkgInstaller::register(app.handle());
             Ok(())
         })
         .run(tauri::generate_context!())
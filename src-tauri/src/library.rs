@@ -0,0 +1,6 @@
+pub(crate) mod command;
+mod game;
+pub mod roots;
+mod scan;
+pub mod stats;
+pub mod watcher;
@@ -0,0 +1,186 @@
+use crate::db;
+use crate::library::roots::{self, LibraryRoot, RootSettings};
+use crate::library::scan::{DEFAULT_MAX_DEPTH, LibraryEvent, scan};
+use crate::library::watcher::LibraryWatcherState;
+use anyhow::anyhow;
+use log::{error, info};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri::ipc::Channel;
+use tauri_plugin_fs::FilePath;
+
+fn into_paths(roots: Vec<FilePath>) -> anyhow::Result<Vec<PathBuf>> {
+    roots
+        .into_iter()
+        .map(|r| r.into_path().map_err(|e| anyhow!("invalid root: {}", e)))
+        .collect()
+}
+
+fn send_event(on_event: &Channel<LibraryEvent>, ev: LibraryEvent) {
+    if let Err(e) = on_event.send(ev) {
+        error!("could not send library event to js: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn library_scan(
+    roots: Vec<FilePath>,
+    known_paths: Vec<PathBuf>,
+    max_depth: Option<u32>,
+    on_event: Channel<LibraryEvent>,
+) -> anyhow_tauri::TAResult<()> {
+    let roots = into_paths(roots)?;
+
+    info!("Scanning game library: {:?}", roots);
+    scan(
+        roots,
+        known_paths.into_iter().collect::<HashSet<_>>(),
+        max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+        move |ev| send_event(&on_event, ev),
+    )
+    .await;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn library_list_roots(app_handle: AppHandle) -> anyhow_tauri::TAResult<Vec<LibraryRoot>> {
+    let pool = db::pool(&app_handle).await?;
+    Ok(roots::list(&pool).await.map_err(anyhow::Error::from)?)
+}
+
+/// Adds a root to the library, or updates its settings if it was already added
+#[tauri::command]
+pub async fn library_add_root(
+    app_handle: AppHandle,
+    watcher: LibraryWatcherState<'_>,
+    path: FilePath,
+    settings: Option<RootSettings>,
+) -> anyhow_tauri::TAResult<LibraryRoot> {
+    let path = path
+        .into_path()
+        .map_err(|e| anyhow!("invalid root: {}", e))?;
+    let pool = db::pool(&app_handle).await?;
+    if let Some(other) = roots::find_overlapping(&pool, &path)
+        .await
+        .map_err(anyhow::Error::from)?
+    {
+        return Err(anyhow!(
+            "game root {} overlaps with the game root {}",
+            path.display(),
+            other.path.display()
+        )
+        .into());
+    }
+    let root = roots::add(&pool, &path, &settings.unwrap_or_default())
+        .await
+        .map_err(anyhow::Error::from)?;
+    info!("Added game root {}", root.path.display());
+
+    let mut watcher = watcher.lock().await;
+    if watcher.is_watching() {
+        let roots = roots::list(&pool).await.map_err(anyhow::Error::from)?;
+        watcher
+            .set_roots(&app_handle, roots)
+            .map_err(|e| anyhow!("could not watch game library: {}", e))?;
+    }
+    Ok(root)
+}
+
+/// Removes a root from the library, forgetting the games indexed from it
+#[tauri::command]
+pub async fn library_remove_root(
+    app_handle: AppHandle,
+    watcher: LibraryWatcherState<'_>,
+    id: i64,
+) -> anyhow_tauri::TAResult<()> {
+    let pool = db::pool(&app_handle).await?;
+    let Some(root) = roots::remove(&pool, id)
+        .await
+        .map_err(anyhow::Error::from)?
+    else {
+        return Err(anyhow!("game root {} does not exist", id).into());
+    };
+    info!("Removed game root {}", root.path.display());
+
+    let mut watcher = watcher.lock().await;
+    if watcher.is_watching() {
+        let roots = roots::list(&pool).await.map_err(anyhow::Error::from)?;
+        watcher
+            .set_roots(&app_handle, roots)
+            .map_err(|e| anyhow!("could not watch game library: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Scans a single root using its own settings
+#[tauri::command]
+pub async fn library_rescan_root(
+    app_handle: AppHandle,
+    watcher: LibraryWatcherState<'_>,
+    id: i64,
+    known_paths: Vec<PathBuf>,
+    on_event: Channel<LibraryEvent>,
+) -> anyhow_tauri::TAResult<()> {
+    let pool = db::pool(&app_handle).await?;
+    let Some(root) = roots::get(&pool, id).await.map_err(anyhow::Error::from)? else {
+        return Err(anyhow!("game root {} does not exist", id).into());
+    };
+    if !root.settings.enabled {
+        send_event(&on_event, LibraryEvent::Finished);
+        return Ok(());
+    }
+
+    info!("Rescanning game root {}", root.path.display());
+    // The watcher is only locked once the scan is done, so changes keep being handled meanwhile
+    let mut found = Vec::new();
+    let mut removed = Vec::new();
+    scan(
+        vec![root.path],
+        known_paths.into_iter().collect::<HashSet<_>>(),
+        root.settings.max_depth,
+        |ev| {
+            match &ev {
+                LibraryEvent::Discovered { game } => found.push(game.clone()),
+                LibraryEvent::Removed { path } => removed.push(path.clone()),
+                _ => {}
+            }
+            send_event(&on_event, ev);
+        },
+    )
+    .await;
+
+    let watcher = watcher.lock().await;
+    for game in &found {
+        watcher.track(game);
+    }
+    for path in &removed {
+        watcher.untrack(path);
+    }
+
+    Ok(())
+}
+
+/// Watches the enabled roots for changes, emitting [`crate::library::watcher::WATCH_EVENT`] events
+#[tauri::command]
+pub async fn library_watch(
+    app_handle: AppHandle,
+    watcher: LibraryWatcherState<'_>,
+    known_paths: Vec<PathBuf>,
+) -> anyhow_tauri::TAResult<()> {
+    let pool = db::pool(&app_handle).await?;
+    let roots = roots::list(&pool).await.map_err(anyhow::Error::from)?;
+    watcher
+        .lock()
+        .await
+        .watch(&app_handle, roots, known_paths)
+        .map_err(|e| anyhow!("could not watch game library: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn library_unwatch(watcher: LibraryWatcherState<'_>) -> anyhow_tauri::TAResult<()> {
+    watcher.lock().await.unwatch();
+    Ok(())
+}
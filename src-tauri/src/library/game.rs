@@ -0,0 +1,117 @@
+use crate::file_format::pkg::PKG;
+use crate::file_format::psf::PSF;
+use crate::file_format::psf::param_sfo::ParamSfo;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folders with the game executable are considered games
+pub const GAME_EXECUTABLE: &str = "eboot.bin";
+
+/// Packages are listed next to the installed games, read from the package itself
+pub const PACKAGE_EXTENSION: &str = "pkg";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameInfo {
+    pub path: PathBuf,
+    pub cusa: String,
+    pub title: String,
+    pub version: String,
+    pub fw_version: String,
+    pub sfo: Option<PSF>,
+    /// `path` is a package that is not installed
+    pub package: bool,
+}
+
+impl GameInfo {
+    pub fn param_sfo_path(path: &Path) -> PathBuf {
+        path.join("sce_sys").join("param.sfo")
+    }
+
+    /// The file the game information is read from
+    pub fn info_path(path: &Path) -> PathBuf {
+        if is_package(path) {
+            path.to_owned()
+        } else {
+            Self::param_sfo_path(path)
+        }
+    }
+
+    /// Reads the game information from its param.sfo.
+    /// Games without one are still listed, using the folder name
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if is_package(path) {
+            return Self::load_package(path);
+        }
+
+        let base = path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let param_sfo = Self::param_sfo_path(path);
+        if !param_sfo.exists() {
+            return Ok(Self {
+                path: path.to_owned(),
+                cusa: format!("N/A - {}", base),
+                title: base,
+                version: "N/A".to_owned(),
+                fw_version: "N/A".to_owned(),
+                sfo: None,
+                package: false,
+            });
+        }
+
+        let psf = PSF::open(&param_sfo)?;
+        Ok(Self::from_psf(path, psf))
+    }
+
+    /// Reads the game information from the param.sfo inside the package
+    pub fn load_package(path: &Path) -> anyhow::Result<Self> {
+        let mut pkg = PKG::open(path)?;
+        let mut psf = pkg.param_sfo()?;
+        psf.set_last_write(fs::metadata(path)?.modified()?);
+        Ok(Self {
+            package: true,
+            ..Self::from_psf(path, psf)
+        })
+    }
+
+    pub fn from_psf(path: &Path, psf: PSF) -> Self {
+        let sfo = ParamSfo::new(&psf);
+        let base = || {
+            path.file_name()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        Self {
+            path: path.to_owned(),
+            cusa: sfo.title_id().map_or_else(base, str::to_owned),
+            title: sfo.title().unwrap_or("Unknown").to_owned(),
+            version: sfo.app_ver().unwrap_or("N/A").to_owned(),
+            fw_version: sfo
+                .system_ver()
+                .map_or_else(|| "UNK".to_owned(), |v| v.to_string()),
+            sfo: Some(psf),
+            package: false,
+        }
+    }
+}
+
+/// Game folders and packages
+pub fn is_game(path: &Path) -> bool {
+    path.join(GAME_EXECUTABLE).exists() || is_package(path)
+}
+
+pub fn is_package(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(PACKAGE_EXTENSION))
+        && path.is_file()
+}
+
+/// Update and patch folders are installed next to the game but aren't games by themselves
+pub fn is_ignored(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.ends_with("-UPDATE") || name.ends_with("-patch")
+}
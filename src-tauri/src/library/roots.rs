@@ -0,0 +1,130 @@
+use crate::library::scan::DEFAULT_MAX_DEPTH;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+/// A folder games are indexed from
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRoot {
+    pub id: i64,
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub settings: RootSettings,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RootSettings {
+    pub max_depth: u32,
+    pub enabled: bool,
+    /// The launcher never writes into read-only roots, e.g. when installing packages
+    pub read_only: bool,
+}
+
+impl Default for RootSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            enabled: true,
+            read_only: false,
+        }
+    }
+}
+
+impl LibraryRoot {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            path: PathBuf::from(row.try_get::<String, _>("path")?),
+            settings: RootSettings {
+                max_depth: row.try_get("max_depth")?,
+                enabled: row.try_get("enabled")?,
+                read_only: row.try_get("read_only")?,
+            },
+        })
+    }
+}
+
+pub async fn list(pool: &SqlitePool) -> sqlx::Result<Vec<LibraryRoot>> {
+    sqlx::query("SELECT * FROM library_roots ORDER BY id")
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(LibraryRoot::from_row)
+        .collect()
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> sqlx::Result<Option<LibraryRoot>> {
+    sqlx::query("SELECT * FROM library_roots WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .as_ref()
+        .map(LibraryRoot::from_row)
+        .transpose()
+}
+
+/// Finds another root containing the path or inside it. Games are matched to their
+/// root by path prefix, so roots can't be nested
+pub async fn find_overlapping(pool: &SqlitePool, path: &Path) -> sqlx::Result<Option<LibraryRoot>> {
+    Ok(list(pool)
+        .await?
+        .into_iter()
+        .find(|r| r.path != path && (path.starts_with(&r.path) || r.path.starts_with(path))))
+}
+
+/// Adds a root, or replaces the settings if it's already in the library
+pub async fn add(
+    pool: &SqlitePool,
+    path: &Path,
+    settings: &RootSettings,
+) -> sqlx::Result<LibraryRoot> {
+    let row = sqlx::query(
+        "INSERT INTO library_roots (path, max_depth, enabled, read_only) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (path) DO UPDATE SET max_depth = $2, enabled = $3, read_only = $4
+         RETURNING *",
+    )
+    .bind(path.to_string_lossy())
+    .bind(settings.max_depth)
+    .bind(settings.enabled)
+    .bind(settings.read_only)
+    .fetch_one(pool)
+    .await?;
+    LibraryRoot::from_row(&row)
+}
+
+/// Removes the root and the games indexed from it
+pub async fn remove(pool: &SqlitePool, id: i64) -> sqlx::Result<Option<LibraryRoot>> {
+    let mut tx = pool.begin().await?;
+    let Some(root) = sqlx::query("DELETE FROM library_roots WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .as_ref()
+        .map(LibraryRoot::from_row)
+        .transpose()?
+    else {
+        return Ok(None);
+    };
+
+    let mut prefix = root.path.to_string_lossy().into_owned();
+    if !prefix.ends_with(MAIN_SEPARATOR) {
+        prefix.push(MAIN_SEPARATOR);
+    }
+    sqlx::query("DELETE FROM games WHERE substr(path, 1, length($1)) = $1")
+        .bind(prefix)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(root))
+}
+
+/// Finds a read-only root containing the path
+pub async fn find_read_only(pool: &SqlitePool, path: &Path) -> sqlx::Result<Option<LibraryRoot>> {
+    Ok(list(pool)
+        .await?
+        .into_iter()
+        .find(|r| r.settings.read_only && path.starts_with(&r.path)))
+}
@@ -0,0 +1,106 @@
+use crate::library::game::{GameInfo, is_game, is_ignored, is_package};
+use log::{debug, error};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::task::JoinSet;
+
+/// How many folders are read at the same time. Mostly useful for network drives
+const SCAN_PARALLELISM: usize = 16;
+
+pub const DEFAULT_MAX_DEPTH: u32 = 3;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event")]
+pub enum LibraryEvent {
+    Discovered { game: GameInfo },
+    Removed { path: PathBuf },
+    Error { path: PathBuf, err: String },
+    Finished,
+}
+
+enum Visit {
+    Game(Box<GameInfo>),
+    Dir(Vec<PathBuf>),
+}
+
+fn visit(path: &Path) -> anyhow::Result<Visit> {
+    if is_game(path) {
+        return Ok(Visit::Game(Box::new(GameInfo::load(path)?)));
+    }
+    let mut children = Vec::new();
+    for c in fs::read_dir(path)? {
+        let c = c?.path();
+        if c.is_dir() || is_package(&c) {
+            children.push(c);
+        }
+    }
+    Ok(Visit::Dir(children))
+}
+
+/// Walks the game roots looking for game folders.
+/// Folders in `known` are not read again, and the ones not found anymore are reported as removed
+pub async fn scan(
+    roots: Vec<PathBuf>,
+    known: HashSet<PathBuf>,
+    max_depth: u32,
+    mut on_event: impl FnMut(LibraryEvent),
+) {
+    let mut seen = HashSet::new();
+    let mut pending: VecDeque<(PathBuf, u32)> = roots.iter().map(|r| (r.clone(), 0)).collect();
+    let mut tasks = JoinSet::new();
+
+    loop {
+        while tasks.len() < SCAN_PARALLELISM
+            && let Some((path, depth)) = pending.pop_front()
+        {
+            if depth > max_depth || is_ignored(&path) {
+                continue;
+            }
+            if known.contains(&path) {
+                seen.insert(path);
+                continue;
+            }
+            tasks.spawn_blocking(move || {
+                let r = visit(&path);
+                (path, depth, r)
+            });
+        }
+
+        let Some(r) = tasks.join_next().await else {
+            break;
+        };
+        let (path, depth, r) = match r {
+            Ok(r) => r,
+            Err(e) => {
+                error!("library scan task failed: {}", e);
+                continue;
+            }
+        };
+        match r {
+            Ok(Visit::Game(game)) => {
+                debug!("found game at {}", path.display());
+                seen.insert(path);
+                on_event(LibraryEvent::Discovered { game: *game });
+            }
+            Ok(Visit::Dir(children)) => {
+                pending.extend(children.into_iter().map(|c| (c, depth + 1)));
+            }
+            Err(e) => {
+                error!("error discovering game at {}: {}", path.display(), e);
+                on_event(LibraryEvent::Error {
+                    path,
+                    err: e.to_string(),
+                });
+            }
+        }
+    }
+
+    for path in known {
+        if !seen.contains(&path) && roots.iter().any(|r| path.starts_with(r)) {
+            on_event(LibraryEvent::Removed { path });
+        }
+    }
+    on_event(LibraryEvent::Finished);
+}
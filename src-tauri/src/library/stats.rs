@@ -0,0 +1,88 @@
+use crate::db;
+use anyhow::Context;
+use sqlx::{Row, SqlitePool};
+use std::path::Path;
+use std::time::Instant;
+use tauri::AppHandle;
+use time::OffsetDateTime;
+
+/// A running game, recorded in `game_sessions` and added to the game stats when it ends
+pub struct PlaySession {
+    pool: SqlitePool,
+    id: i64,
+    game_id: i64,
+    started: Instant,
+}
+
+impl PlaySession {
+    /// Starts a session for the game at `game_path`. Games not in the library are not tracked
+    pub async fn begin(app_handle: &AppHandle, game_path: &Path) -> anyhow::Result<Option<Self>> {
+        let pool = db::pool(app_handle).await?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut tx = pool.begin().await?;
+        let Some(row) = sqlx::query("SELECT id FROM games WHERE path = $1")
+            .bind(game_path.to_string_lossy())
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let game_id: i64 = row.try_get("id")?;
+
+        let id = sqlx::query("INSERT INTO game_sessions (game_id, started_at) VALUES ($1, $2)")
+            .bind(game_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+        sqlx::query(
+            "UPDATE games SET last_launched = $2, launch_count = launch_count + 1 WHERE id = $1",
+        )
+        .bind(game_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await.context("could not start play session")?;
+
+        Ok(Some(Self {
+            pool,
+            id,
+            game_id,
+            started: Instant::now(),
+        }))
+    }
+
+    /// Ends the sessions left open by a previous run of the launcher. How long they
+    /// lasted is unknown, so they end when they started and add no play time
+    pub async fn close_abandoned(app_handle: &AppHandle) -> anyhow::Result<u64> {
+        let pool = db::pool(app_handle).await?;
+        let closed =
+            sqlx::query("UPDATE game_sessions SET ended_at = started_at WHERE ended_at IS NULL")
+                .execute(&pool)
+                .await
+                .context("could not close abandoned play sessions")?
+                .rows_affected();
+        Ok(closed)
+    }
+
+    pub async fn end(self, exit_status: i32) -> anyhow::Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let play_time = self.started.elapsed().as_secs() as i64;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE game_sessions SET ended_at = $2, exit_status = $3 WHERE id = $1")
+            .bind(self.id)
+            .bind(now)
+            .bind(exit_status)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE games SET play_time = play_time + $2 WHERE id = $1")
+            .bind(self.game_id)
+            .bind(play_time)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await.context("could not end play session")?;
+        Ok(())
+    }
+}
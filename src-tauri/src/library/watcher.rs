@@ -0,0 +1,313 @@
+use crate::library::game::{GameInfo, is_game, is_ignored};
+use crate::library::roots::LibraryRoot;
+use crate::library::scan::{LibraryEvent, scan};
+use log::{debug, error, info, warn};
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{DebounceEventResult, Debouncer, RecommendedCache, new_debouncer};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tauri::async_runtime::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+
+/// Global event emitted with [`WatchEvent`] payloads
+pub const WATCH_EVENT: &str = "library://watch";
+
+/// Copying a game creates thousands of events, they are only handled after the folder settles
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub type LibraryWatcherState<'a> = State<'a, Mutex<LibraryWatcher>>;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event")]
+pub enum WatchEvent {
+    Added { game: GameInfo },
+    Updated { game: GameInfo },
+    Removed { path: PathBuf },
+}
+
+struct WatchedLibrary {
+    /// Enabled roots and their max depth
+    roots: Vec<(PathBuf, u32)>,
+    /// Known game folders and packages, and the param.sfo (or package) mtime they were read with
+    games: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+type LibraryDebouncer = Debouncer<RecommendedWatcher, RecommendedCache>;
+
+pub struct LibraryWatcher {
+    debouncer: Option<LibraryDebouncer>,
+    library: Arc<std::sync::Mutex<WatchedLibrary>>,
+}
+
+/// Stopping waits for the debouncer thread, so it's done outside the watcher lock
+fn stop_debouncer(debouncer: Option<LibraryDebouncer>) {
+    if let Some(debouncer) = debouncer {
+        tauri::async_runtime::spawn_blocking(move || debouncer.stop());
+    }
+}
+
+impl LibraryWatcher {
+    pub fn register(app_handle: &AppHandle) {
+        let state = LibraryWatcher {
+            debouncer: None,
+            library: Arc::new(std::sync::Mutex::new(WatchedLibrary {
+                roots: Vec::new(),
+                games: HashMap::new(),
+            })),
+        };
+        app_handle.manage(Mutex::new(state));
+    }
+
+    /// Starts watching the roots. `known` are the game folders the frontend already has
+    pub fn watch(
+        &mut self,
+        app_handle: &AppHandle,
+        roots: Vec<LibraryRoot>,
+        known: impl IntoIterator<Item = PathBuf>,
+    ) -> notify_debouncer_full::notify::Result<()> {
+        self.library.lock().unwrap().games = known
+            .into_iter()
+            .map(|p| {
+                let mtime = sfo_mtime(&p);
+                (p, mtime)
+            })
+            .collect();
+        self.set_roots(app_handle, roots)
+    }
+
+    /// Replaces the watched roots, keeping the known games inside them
+    pub fn set_roots(
+        &mut self,
+        app_handle: &AppHandle,
+        roots: Vec<LibraryRoot>,
+    ) -> notify_debouncer_full::notify::Result<()> {
+        stop_debouncer(self.debouncer.take());
+        let roots = roots
+            .into_iter()
+            .filter(|r| r.settings.enabled)
+            .map(|r| (r.path, r.settings.max_depth))
+            .collect::<Vec<_>>();
+        {
+            let mut library = self.library.lock().unwrap();
+            library
+                .games
+                .retain(|g, _| roots.iter().any(|(r, _)| g.starts_with(r)));
+            library.roots = roots.clone();
+        }
+
+        // Changes are handled by a task, the debouncer thread only hands them over.
+        // The task ends when the debouncer is dropped
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app_handle = app_handle.clone();
+        let library = self.library.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(paths) = rx.recv().await {
+                handle_changes(&app_handle, &library, paths).await;
+            }
+        });
+        let mut debouncer = new_debouncer(
+            DEBOUNCE_TIMEOUT,
+            None,
+            move |r: DebounceEventResult| match r {
+                Ok(events) => {
+                    let paths = events
+                        .into_iter()
+                        .flat_map(|e| e.event.paths)
+                        .collect::<HashSet<_>>();
+                    let _ = tx.send(paths);
+                }
+                Err(errors) => {
+                    for e in errors {
+                        error!("library watcher error: {}", e);
+                    }
+                }
+            },
+        )?;
+        for (root, _) in &roots {
+            info!("Watching game root {}", root.display());
+            if let Err(e) = debouncer.watch(root, RecursiveMode::Recursive) {
+                error!("could not watch game root {}: {}", root.display(), e);
+            }
+        }
+        self.debouncer = Some(debouncer);
+        Ok(())
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.debouncer.is_some()
+    }
+
+    pub fn unwatch(&mut self) {
+        stop_debouncer(self.debouncer.take());
+        let mut library = self.library.lock().unwrap();
+        library.roots.clear();
+        library.games.clear();
+    }
+
+    /// Registers a game found outside the watcher, e.g. by a rescan
+    pub fn track(&self, game: &GameInfo) {
+        let last_write = game.sfo.as_ref().map(|s| s.last_write());
+        self.library
+            .lock()
+            .unwrap()
+            .games
+            .insert(game.path.clone(), last_write);
+    }
+
+    pub fn untrack(&self, path: &Path) {
+        self.library.lock().unwrap().games.remove(path);
+    }
+}
+
+fn sfo_mtime(game: &Path) -> Option<SystemTime> {
+    fs::metadata(GameInfo::info_path(game))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+fn emit_event(app_handle: &AppHandle, ev: WatchEvent) {
+    if let Err(e) = app_handle.emit(WATCH_EVENT, ev) {
+        error!("could not emit library watch event: {}", e);
+    }
+}
+
+/// Runs once per settled batch of changes
+async fn handle_changes(
+    app_handle: &AppHandle,
+    library: &Arc<std::sync::Mutex<WatchedLibrary>>,
+    paths: HashSet<PathBuf>,
+) {
+    let new_dirs = {
+        let app_handle = app_handle.clone();
+        let library = library.clone();
+        match tokio::task::spawn_blocking(move || reload_games(&app_handle, &library, paths)).await
+        {
+            Ok(new_dirs) => new_dirs,
+            Err(e) => {
+                error!("library watcher task failed: {}", e);
+                return;
+            }
+        }
+    };
+
+    for (dir, depth) in new_dirs {
+        let known = library.lock().unwrap().games.keys().cloned().collect();
+        scan(vec![dir], known, depth, |ev| {
+            if let LibraryEvent::Discovered { game } = ev {
+                let last_write = game.sfo.as_ref().map(|s| s.last_write());
+                library
+                    .lock()
+                    .unwrap()
+                    .games
+                    .insert(game.path.clone(), last_write);
+                emit_event(app_handle, WatchEvent::Added { game });
+            }
+        })
+        .await;
+    }
+}
+
+/// Reloads the changed games and loads the new ones. Returns the new folders
+/// that may have games inside, with the depth left to scan them
+fn reload_games(
+    app_handle: &AppHandle,
+    library: &std::sync::Mutex<WatchedLibrary>,
+    paths: HashSet<PathBuf>,
+) -> Vec<(PathBuf, u32)> {
+    let mut affected = HashSet::new();
+    let mut new_games = HashSet::new();
+    let mut new_dirs = Vec::new();
+    {
+        let library = library.lock().unwrap();
+        for path in paths {
+            if let Some(game) = library.games.keys().find(|g| path.starts_with(g)) {
+                affected.insert(game.clone());
+                continue;
+            }
+            // A parent folder of known games was moved or deleted
+            affected.extend(
+                library
+                    .games
+                    .keys()
+                    .filter(|g| g.starts_with(&path))
+                    .cloned(),
+            );
+
+            let Some((root, max_depth)) = library.roots.iter().find(|(r, _)| path.starts_with(r))
+            else {
+                continue;
+            };
+            // Game files can arrive in any order, so the game folder is only
+            // complete once the executable shows up
+            let mut parent = path.as_path();
+            let mut ignored = false;
+            let mut found = None;
+            while parent.starts_with(root) && parent != root.as_path() {
+                ignored |= is_ignored(parent);
+                if found.is_none() && is_game(parent) {
+                    found = Some(parent.to_owned());
+                }
+                parent = parent.parent().unwrap_or(root);
+            }
+            if ignored {
+                continue;
+            }
+            let depth =
+                |p: &Path| p.strip_prefix(root).map_or(0, |p| p.components().count()) as u32;
+            // Same limit as a scan of the root
+            if let Some(game) = found {
+                if depth(&game) <= *max_depth {
+                    new_games.insert(game);
+                }
+            } else if path.is_dir()
+                && let Some(remaining) = max_depth.checked_sub(depth(&path))
+            {
+                new_dirs.push((path, remaining));
+            }
+        }
+    }
+
+    let emit = |ev: WatchEvent| emit_event(app_handle, ev);
+
+    for game in affected {
+        if !is_game(&game) {
+            debug!("game removed at {}", game.display());
+            library.lock().unwrap().games.remove(&game);
+            emit(WatchEvent::Removed { path: game });
+            continue;
+        }
+        let mtime = sfo_mtime(&game);
+        let cached = library.lock().unwrap().games.get(&game).copied().flatten();
+        if mtime == cached {
+            continue;
+        }
+        match GameInfo::load(&game) {
+            Ok(info) => {
+                debug!("game updated at {}", game.display());
+                let last_write = info.sfo.as_ref().map(|s| s.last_write());
+                library.lock().unwrap().games.insert(game, last_write);
+                emit(WatchEvent::Updated { game: info });
+            }
+            Err(e) => warn!("could not reload game at {}: {}", game.display(), e),
+        }
+    }
+
+    for game in new_games {
+        match GameInfo::load(&game) {
+            Ok(info) => {
+                debug!("game added at {}", game.display());
+                let last_write = info.sfo.as_ref().map(|s| s.last_write());
+                library.lock().unwrap().games.insert(game, last_write);
+                emit(WatchEvent::Added { game: info });
+            }
+            Err(e) => warn!("could not load game at {}: {}", game.display(), e),
+        }
+    }
+
+    new_dirs
+}
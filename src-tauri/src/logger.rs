@@ -111,26 +111,23 @@ pub fn build_log_plugin() -> TauriPlugin<Wry> {
 
     let log_plugin = log_plugin.format(move |out, message, record| {
         let target = record.target();
-        if target.starts_with("webview") {
-            match (
+        if target.starts_with("webview")
+            && let (Some(file), Some(line), Some(column)) = (
                 record.file(),
                 record.line(),
                 record.key_values().get(column_key.to_key()),
-            ) {
-                (Some(file), Some(line), Some(column)) => {
-                    out.finish(format_args!(
-                        "{}[{}][webview {}:{}:{}] {}",
-                        tz.get_now().format(&format).unwrap(),
-                        record.level(),
-                        file,
-                        line,
-                        column,
-                        message
-                    ));
-                    return;
-                }
-                _ => {}
-            }
+            )
+        {
+            out.finish(format_args!(
+                "{}[{}][webview {}:{}:{}] {}",
+                tz.get_now().format(&format).unwrap(),
+                record.level(),
+                file,
+                line,
+                column,
+                message
+            ));
+            return;
         }
         out.finish(format_args!(
             "{}[{}][{}] {}",
@@ -0,0 +1,361 @@
+use crate::db;
+use crate::file_format::pfs::PfsFileKind;
+use crate::file_format::pkg::{self, PKG, PkgKeys, PkgKeyset};
+use crate::file_format::psf::param_sfo::{Category, ParamSfo};
+use crate::library::roots;
+use anyhow::{Context, anyhow};
+use anyhow_tauri::IntoTAResult;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::async_runtime::Mutex;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_fs::FilePath;
+
+/// Written in the install folder while an install is in progress, so it can be resumed
+const INSTALL_MARKER: &str = ".pkg_install.json";
+
+/// Package entries extracted next to the game files, when the image doesn't have them
+const SCE_SYS_ENTRIES: &[&str] = &[
+    "param.sfo",
+    "pic0.png",
+    "pic1.png",
+    "icon0.png",
+    "snd0.at9",
+    "changeinfo/changeinfo.xml",
+    "nptitle.dat",
+    "npbind.dat",
+    "playgo-chunk.dat",
+    "pronunciation.xml",
+    "pronunciation.sig",
+    "pubtoolinfo.dat",
+    "shareparam.json",
+];
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event")]
+pub enum InstallEvent {
+    Started {
+        path: PathBuf,
+        total_bytes: u64,
+        resumed: bool,
+    },
+    Progress {
+        current: String,
+        done_bytes: u64,
+        total_bytes: u64,
+    },
+    Finished {
+        path: PathBuf,
+    },
+    Cancelled,
+}
+
+/// PEM files of the [`PkgKeyset`]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PkgKeyFiles {
+    derived_key3: FilePath,
+    fake: FilePath,
+}
+
+impl PkgKeyFiles {
+    fn load(self) -> anyhow::Result<PkgKeyset> {
+        let read = |path: FilePath| -> anyhow::Result<String> {
+            let path = path.into_path()?;
+            fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))
+        };
+        Ok(PkgKeyset::from_pem(
+            &read(self.derived_key3)?,
+            &read(self.fake)?,
+        )?)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct InstallMarker {
+    content_id: String,
+    completed: HashSet<String>,
+}
+
+impl InstallMarker {
+    fn load(dir: &Path) -> Option<Self> {
+        let data = fs::read(dir.join(INSTALL_MARKER)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        let data = serde_json::to_vec(self)?;
+        fs::write(dir.join(INSTALL_MARKER), data).context("could not write install marker")
+    }
+}
+
+/// Writes the files of an install, keeping the marker and the progress up to date
+struct Extraction<'a> {
+    dest: PathBuf,
+    marker: InstallMarker,
+    done_bytes: u64,
+    total_bytes: u64,
+    cancel: &'a AtomicBool,
+    on_event: &'a Channel<InstallEvent>,
+}
+
+impl Extraction<'_> {
+    /// Writes a file with `copy`, which gets the progress callback of the copy methods.
+    /// Returns `false` when cancelled
+    fn write(
+        &mut self,
+        path: &str,
+        copy: impl FnOnce(&mut BufWriter<File>, &mut dyn FnMut(usize) -> bool) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        if self.marker.completed.contains(path) {
+            return Ok(true);
+        }
+        let out_path = self.dest.join(path);
+        if let Some(p) = out_path.parent() {
+            fs::create_dir_all(p)?;
+        }
+
+        let mut out = BufWriter::new(File::create(&out_path)?);
+        let finished = copy(&mut out, &mut |n| {
+            self.done_bytes += n as u64;
+            let _ = self.on_event.send(InstallEvent::Progress {
+                current: path.to_owned(),
+                done_bytes: self.done_bytes,
+                total_bytes: self.total_bytes,
+            });
+            !self.cancel.load(Ordering::Relaxed)
+        })?;
+        out.flush()?;
+        if !finished {
+            // The partial file is written again when resuming
+            let _ = self.on_event.send(InstallEvent::Cancelled);
+            return Ok(false);
+        }
+        self.marker.completed.insert(path.to_owned());
+        self.marker.save(&self.dest)?;
+        Ok(true)
+    }
+}
+
+/// Installs running in the background, by package path
+pub struct PkgInstaller {
+    running: HashMap<PathBuf, Arc<AtomicBool>>,
+}
+
+pub type PkgInstallerState<'a> = State<'a, Mutex<PkgInstaller>>;
+
+impl PkgInstaller {
+    pub fn register(app_handle: &AppHandle) {
+        let state = PkgInstaller {
+            running: Default::default(),
+        };
+        app_handle.manage(Mutex::new(state));
+    }
+}
+
+fn install_dir(pkg: &mut PKG<File>, games_path: &Path) -> anyhow::Result<PathBuf> {
+    let psf = pkg.param_sfo()?;
+    let sfo = ParamSfo::new(&psf);
+    let title_id = sfo
+        .title_id()
+        .map(str::to_owned)
+        .or_else(|| pkg.title_id())
+        .ok_or(anyhow!("package has no title id"))?;
+    // Same convention used by the emulator for patches installed next to the base game
+    let dir_name = match sfo.category() {
+        Some(Category::Patch) => format!("{}-UPDATE", title_id),
+        _ => title_id,
+    };
+    Ok(games_path.join(dir_name))
+}
+
+fn install(
+    pkg_path: &Path,
+    games_path: &Path,
+    keyset: Option<&PkgKeyset>,
+    overwrite: bool,
+    cancel: &AtomicBool,
+    on_event: &Channel<InstallEvent>,
+) -> anyhow::Result<()> {
+    let mut pkg = PKG::open(pkg_path)?;
+    let keys: Option<PkgKeys> = keyset.map(|k| pkg.keys(k)).transpose()?;
+
+    // Read before writing anything, so an image that can't be extracted leaves no folder behind
+    let files = pkg.pfs(keys.as_ref())?.files()?;
+
+    let dest = install_dir(&mut pkg, games_path)?;
+    let content_id = pkg.content_id();
+
+    let marker = match InstallMarker::load(&dest) {
+        Some(m) if m.content_id == content_id => m,
+        Some(_) | None if dest.exists() && !overwrite => {
+            return Err(anyhow!("already installed: {}", dest.display()));
+        }
+        _ => InstallMarker {
+            content_id,
+            ..Default::default()
+        },
+    };
+    let resumed = !marker.completed.is_empty();
+
+    // The image has its own sce_sys folder, the package entries only fill the gaps
+    let image_paths: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let entries: Vec<(String, pkg::Entry)> = SCE_SYS_ENTRIES
+        .iter()
+        .filter_map(|name| pkg.find_entry(name).cloned())
+        .filter(|e| !e.encrypted || keys.is_some())
+        .map(|e| {
+            (
+                format!("sce_sys/{}", e.name.as_deref().unwrap_or_default()),
+                e,
+            )
+        })
+        .filter(|(path, _)| !image_paths.contains(path.as_str()))
+        .collect();
+
+    let sizes = entries
+        .iter()
+        .map(|(path, e)| (path.as_str(), e.size as u64))
+        .chain(files.iter().map(|f| (f.path.as_str(), f.size)));
+    let (mut total_bytes, mut done_bytes) = (0, 0);
+    for (path, size) in sizes {
+        total_bytes += size;
+        if marker.completed.contains(path) {
+            done_bytes += size;
+        }
+    }
+
+    fs::create_dir_all(dest.join("sce_sys"))?;
+    marker.save(&dest)?;
+    let _ = on_event.send(InstallEvent::Started {
+        path: dest.clone(),
+        total_bytes,
+        resumed,
+    });
+
+    let mut extraction = Extraction {
+        dest,
+        marker,
+        done_bytes,
+        total_bytes,
+        cancel,
+        on_event,
+    };
+    for (path, entry) in &entries {
+        let finished = extraction.write(path, |out, on_chunk| match &keys {
+            Some(keys) if entry.encrypted => {
+                let data = pkg.decrypt_entry(entry.name.as_deref().unwrap_or_default(), keys)?;
+                out.write_all(&data)?;
+                Ok(on_chunk(data.len()))
+            }
+            _ => Ok(pkg.copy_entry(entry, out, on_chunk)?),
+        })?;
+        if !finished {
+            return Ok(());
+        }
+    }
+
+    let mut pfs = pkg.pfs(keys.as_ref())?;
+    for file in &files {
+        if file.kind == PfsFileKind::Dir {
+            fs::create_dir_all(extraction.dest.join(&file.path))?;
+            continue;
+        }
+        let finished = extraction.write(&file.path, |out, on_block| {
+            Ok(pfs.copy_file(file, out, on_block)?)
+        })?;
+        if !finished {
+            return Ok(());
+        }
+    }
+
+    let dest = extraction.dest;
+    fs::remove_file(dest.join(INSTALL_MARKER))?;
+    let _ = on_event.send(InstallEvent::Finished { path: dest });
+    Ok(())
+}
+
+/// Installs a package into `games_path`. An existing install is only replaced when `overwrite` is set,
+/// while an interrupted install of the same package is resumed.
+/// `keys` are needed for fake-signed packages, whose image is encrypted
+#[tauri::command]
+pub async fn pkg_install(
+    app_handle: AppHandle,
+    state: PkgInstallerState<'_>,
+    pkg_path: FilePath,
+    games_path: FilePath,
+    keys: Option<PkgKeyFiles>,
+    overwrite: bool,
+    on_event: Channel<InstallEvent>,
+) -> anyhow_tauri::TAResult<()> {
+    let pkg_path = pkg_path
+        .into_path()
+        .map_err(|e| anyhow!("invalid pkg path: {}", e))?;
+    let games_path = games_path
+        .into_path()
+        .map_err(|e| anyhow!("invalid games path: {}", e))?;
+    let keyset = keys.map(PkgKeyFiles::load).transpose()?;
+
+    let pool = db::pool(&app_handle).await?;
+    if let Some(root) = roots::find_read_only(&pool, &games_path)
+        .await
+        .map_err(anyhow::Error::from)?
+    {
+        return Err(anyhow!("game root {} is read-only", root.path.display())).into_ta_result();
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut state = state.lock().await;
+        if state.running.contains_key(&pkg_path) {
+            return Err(anyhow!("package is already being installed")).into_ta_result();
+        }
+        state.running.insert(pkg_path.clone(), cancel.clone());
+    }
+
+    info!("Installing package: {}", pkg_path.display());
+    let r = {
+        let pkg_path = pkg_path.clone();
+        tokio::task::spawn_blocking(move || {
+            install(
+                &pkg_path,
+                &games_path,
+                keyset.as_ref(),
+                overwrite,
+                &cancel,
+                &on_event,
+            )
+        })
+        .await
+    };
+
+    state.lock().await.running.remove(&pkg_path);
+
+    r.into_ta_result()?
+        .inspect_err(|e| error!("could not install package: err={:#}", e))
+        .into_ta_result()
+}
+
+#[tauri::command]
+pub async fn pkg_install_cancel(
+    state: PkgInstallerState<'_>,
+    pkg_path: FilePath,
+) -> anyhow_tauri::TAResult<()> {
+    let pkg_path = pkg_path
+        .into_path()
+        .map_err(|e| anyhow!("invalid pkg path: {}", e))?;
+    let state = state.lock().await;
+    let Some(cancel) = state.running.get(&pkg_path) else {
+        return Err(anyhow!("package is not being installed")).into_ta_result();
+    };
+    cancel.store(true, Ordering::Relaxed);
+    Ok(())
+}
@@ -27,7 +27,6 @@ fn extract_zip_internal(zip_path: &Path, extract_path: &Path) -> anyhow::Result<
             fs::create_dir_all(&final_path)?;
         } else {
             if let Some(p) = final_path.parent() {
-                if !p.exists() {}
                 fs::create_dir_all(p)?
             }
             let mut out_file = File::create(&final_path)?;
@@ -71,7 +70,7 @@ pub fn make_it_executable(path: FilePath) -> anyhow_tauri::TAResult<()> {
             .as_path()
             .ok_or(anyhow::anyhow!("`path` is not a valid path"))
             .into_ta_result()?;
-        fs::set_permissions(&path, fs::Permissions::from_mode(0o744))
+        fs::set_permissions(path, fs::Permissions::from_mode(0o744))
             .map_err(|e| {
                 anyhow::anyhow!(
                     "could not set permission: path={}, err={}",
@@ -93,5 +92,5 @@ pub fn open_path(
     let r = opener.open_path(&path, Some("xdg-open"));
     #[cfg(not(target_os = "linux"))]
     let r = opener.open_path(&path, None::<&str>);
-    r.inspect_err(|e| error!("could not open explorer: path={}, err={}", &path, e))
+    r.inspect_err(|e| error!("could not open explorer: path={}, err={}", path, e))
 }
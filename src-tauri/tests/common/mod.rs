@@ -0,0 +1,63 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use shadps4_launcher_lib::game_process::log::{Level, LogData, LogLimits};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An empty folder of its own for each call, removed when dropped
+pub struct TmpDir(PathBuf);
+
+impl TmpDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        // Tests of the same file run in parallel, and so may other runs of the same file
+        let name = format!(
+            "{}-{}-{}",
+            env!("CARGO_CRATE_NAME"),
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let dir: PathBuf = [env!("CARGO_TARGET_TMPDIR"), "tests", &name]
+            .iter()
+            .collect();
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("test folder should be created");
+        Self(dir)
+    }
+}
+
+impl Deref for TmpDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A log with a row for each line. Lines that are not log lines are `Info` messages of the `UNK` class
+pub fn log_data<S: AsRef<str>>(
+    spill_path: PathBuf,
+    limits: LogLimits,
+    lines: impl IntoIterator<Item = S>,
+) -> LogData {
+    let mut log = LogData::new(limits, spill_path);
+    for line in lines {
+        let line = line.as_ref();
+        let (entry, _) = match log.parse_entry(line) {
+            Some(parsed) => parsed,
+            None => log.new_entry(Level::Info, "UNK", line.to_owned()),
+        };
+        log.add_entry(entry);
+    }
+    log
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::TmpDir;
+use flate2::read::GzDecoder;
+use shadps4_launcher_lib::game_process::log::{
+    ExportFormat, ExportOptions, Level, LogData, LogExporter, LogLimits,
+};
+use std::io::Read;
+
+const LINES: &[&str] = &[
+    "[Core] <Info> starting",
+    "\x1b[0m\x1b[33m[Render.Vulkan] <Warning> slow, \"frame\"\x1b[0m",
+    "not a log line",
+    // Only the reset at the end is colour
+    "[Core] <Error> failed\x1b[0m",
+];
+
+fn log_data(dir: &TmpDir) -> LogData {
+    common::log_data(dir.join("spill.jsonl"), LogLimits::default(), LINES)
+}
+
+fn export(exporter: &LogExporter) -> String {
+    let dir = TmpDir::new();
+    let mut out = Vec::new();
+    exporter
+        .export(&log_data(&dir), &mut out)
+        .expect("export should succeed");
+    String::from_utf8(out).expect("export should be utf-8")
+}
+
+fn options(format: ExportFormat) -> ExportOptions {
+    ExportOptions {
+        format,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn raw_export_strips_or_keeps_ansi() {
+    let stripped = export(&LogExporter::new(options(ExportFormat::Raw)));
+    assert_eq!(
+        stripped,
+        "[Core] <Info> starting\n\
+         [Render.Vulkan] <Warning> slow, \"frame\"\n\
+         not a log line\n\
+         [Core] <Error> failed\n"
+    );
+
+    let kept = export(&LogExporter::new(ExportOptions {
+        keep_ansi: true,
+        ..options(ExportFormat::Raw)
+    }));
+    assert_eq!(kept, LINES.join("\n") + "\n");
+}
+
+#[test]
+fn export_applies_filters() {
+    let exporter = LogExporter::new(options(ExportFormat::Text));
+    let text = export(&exporter.clone().levels(vec![Level::Warning]));
+    assert_eq!(text.lines().count(), 1);
+    assert!(text.contains("[Render.Vulkan] <Warning>"));
+
+    let text = export(&exporter.classes(vec!["Core".to_owned(), "UNK".to_owned()]));
+    assert_eq!(text.lines().count(), 3);
+    assert!(!text.contains("Render.Vulkan"));
+}
+
+#[test]
+fn json_lines_export_has_full_rows() {
+    let rows: Vec<serde_json::Value> = export(&LogExporter::new(options(ExportFormat::JsonLines)))
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line should be json"))
+        .collect();
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[1]["rowId"], 1);
+    assert_eq!(rows[1]["level"], "warning");
+    assert_eq!(rows[1]["class"], "Render.Vulkan");
+    assert!(rows[0]["time"].as_str().is_some_and(|t| t.contains('T')));
+}
+
+#[test]
+fn csv_export_quotes_fields() {
+    let csv = export(&LogExporter::new(options(ExportFormat::Csv)));
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("row_id,time,level,class,message"));
+    let warning = lines.nth(1).expect("the warning row should be exported");
+    assert!(warning.starts_with("1,"));
+    assert!(warning.ends_with(",Warning,Render.Vulkan,\"slow, \"\"frame\"\"\""));
+}
+
+#[test]
+fn gzip_export_decompresses_to_the_same_rows() {
+    let plain = export(&LogExporter::new(options(ExportFormat::Raw)));
+
+    let mut compressed = Vec::new();
+    let exporter = LogExporter::new(ExportOptions {
+        gzip: true,
+        ..options(ExportFormat::Raw)
+    });
+    let dir = TmpDir::new();
+    let rows = exporter
+        .export(&log_data(&dir), &mut compressed)
+        .expect("export should succeed");
+    assert_eq!(rows, 4);
+
+    let mut decompressed = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut decompressed)
+        .expect("export should be valid gzip");
+    assert_eq!(decompressed, plain);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::TmpDir;
+use shadps4_launcher_lib::game_process::log::{LogData, LogLimits, RowId};
+use shadps4_launcher_lib::game_process::search::{
+    MAX_SCANNED_ROWS, SearchMode, SearchQuery, search,
+};
+
+fn log_data<S: AsRef<str>>(dir: &TmpDir, messages: impl IntoIterator<Item = S>) -> LogData {
+    common::log_data(dir.join("spill.jsonl"), LogLimits::default(), messages)
+}
+
+fn query(pattern: &str) -> SearchQuery {
+    SearchQuery {
+        pattern: pattern.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn ranges_are_utf16_offsets() {
+    let dir = TmpDir::new();
+    let log = log_data(&dir, ["abc err", "é err err", "😀 err", "none"]);
+    let result = search(&log, &query("err")).expect("search should succeed");
+    let ranges: Vec<_> = result
+        .matches
+        .iter()
+        .map(|m| (m.entry.row_id, m.ranges.clone()))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, vec![(4, 7)]),
+            (1, vec![(2, 5), (6, 9)]),
+            // The emoji is a surrogate pair
+            (2, vec![(3, 6)]),
+        ]
+    );
+    assert_eq!(result.next_cursor, None);
+}
+
+#[test]
+fn empty_regex_matches_have_no_range() {
+    let dir = TmpDir::new();
+    let log = log_data(&dir, ["abc"]);
+    let result = search(
+        &log,
+        &SearchQuery {
+            mode: SearchMode::Regex,
+            ..query("x*")
+        },
+    )
+    .expect("search should succeed");
+    assert_eq!(result.matches.len(), 1);
+    assert!(result.matches[0].ranges.is_empty());
+}
+
+#[test]
+fn cursor_continues_after_a_full_page() {
+    let dir = TmpDir::new();
+    let log = log_data(&dir, (0..10).map(|i| format!("row {}", i)));
+    let first = search(
+        &log,
+        &SearchQuery {
+            limit: Some(4),
+            ..query("row")
+        },
+    )
+    .expect("search should succeed");
+    assert_eq!(first.matches.len(), 4);
+    assert_eq!(first.next_cursor, Some(4));
+
+    let second = search(
+        &log,
+        &SearchQuery {
+            limit: Some(10),
+            cursor: first.next_cursor,
+            ..query("row")
+        },
+    )
+    .expect("search should succeed");
+    let ids: Vec<RowId> = second.matches.iter().map(|m| m.entry.row_id).collect();
+    assert_eq!(ids, (4..10).collect::<Vec<_>>());
+    assert_eq!(second.next_cursor, None);
+}
+
+#[test]
+fn cursor_is_returned_when_the_scan_cap_is_hit() {
+    let rows = MAX_SCANNED_ROWS as RowId + 10;
+    let dir = TmpDir::new();
+    let log = log_data(
+        &dir,
+        (0..rows).map(|i| {
+            if i == rows - 1 {
+                "needle".to_owned()
+            } else {
+                format!("row {}", i)
+            }
+        }),
+    );
+
+    let first = search(&log, &query("needle")).expect("search should succeed");
+    assert!(first.matches.is_empty());
+    assert_eq!(first.next_cursor, Some(MAX_SCANNED_ROWS as RowId));
+
+    let second = search(
+        &log,
+        &SearchQuery {
+            cursor: first.next_cursor,
+            ..query("needle")
+        },
+    )
+    .expect("search should succeed");
+    assert_eq!(second.matches.len(), 1);
+    assert_eq!(second.matches[0].entry.row_id, rows - 1);
+    assert_eq!(second.next_cursor, None);
+}
+
+#[test]
+fn search_stops_at_to_row_id() {
+    let dir = TmpDir::new();
+    let log = log_data(&dir, (0..10).map(|i| format!("row {}", i)));
+    let result = search(
+        &log,
+        &SearchQuery {
+            from_row_id: Some(2),
+            to_row_id: Some(5),
+            ..query("row")
+        },
+    )
+    .expect("search should succeed");
+    let ids: Vec<RowId> = result.matches.iter().map(|m| m.entry.row_id).collect();
+    assert_eq!(ids, vec![2, 3, 4]);
+    assert_eq!(result.next_cursor, None);
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::TmpDir;
+use shadps4_launcher_lib::game_process::log::{
+    Direction, Entry, LogData, LogLimits, MAX_PAGE_SCANNED_ROWS, RowId,
+};
+use std::fs;
+use std::path::PathBuf;
+
+const ROWS: RowId = 100;
+
+/// Enough rows for the spill index to have more than one entry
+const INDEXED_ROWS: RowId = 3000;
+
+/// Few enough rows are kept in memory that most of them are spilled
+const LIMITS: LogLimits = LogLimits {
+    max_rows: Some(10),
+    max_bytes: None,
+};
+
+fn log_data(spill_path: PathBuf, rows: RowId) -> LogData {
+    common::log_data(spill_path, LIMITS, (0..rows).map(|i| format!("row {}", i)))
+}
+
+fn row_ids(log: &LogData, from: RowId) -> Vec<RowId> {
+    log.rows(from)
+        .expect("rows should be readable")
+        .map(|row| {
+            let (row_id, entry) = row.expect("every row should be readable");
+            assert_eq!(entry.message, format!("row {}", row_id));
+            row_id
+        })
+        .collect()
+}
+
+fn rev_row_ids(log: &LogData, to: RowId) -> Vec<RowId> {
+    log.rows_rev(to)
+        .map(|row| {
+            let (row_id, entry) = row.expect("every row should be readable");
+            assert_eq!(entry.message, format!("row {}", row_id));
+            row_id
+        })
+        .collect()
+}
+
+fn page_ids(log: &LogData, from: RowId, direction: Direction) -> (Vec<RowId>, Option<RowId>) {
+    let page = log
+        .page(Some(from), 5, direction, |_| true)
+        .expect("page should be readable");
+    let ids = page.rows.iter().map(|entry| entry.row_id).collect();
+    (ids, page.next_row_id)
+}
+
+#[test]
+fn rows_cross_the_spill_boundary() {
+    let dir = TmpDir::new();
+    let spill_path = dir.join("spill.jsonl");
+    let log = log_data(spill_path.clone(), ROWS);
+    assert!(spill_path.exists(), "older rows should be spilled");
+
+    assert_eq!(row_ids(&log, 0), (0..ROWS).collect::<Vec<_>>());
+    // The last row always stays in memory
+    assert_eq!(row_ids(&log, ROWS - 1), vec![ROWS - 1]);
+    assert!(row_ids(&log, ROWS).is_empty());
+
+    drop(log);
+    assert!(!spill_path.exists(), "the spill file should be removed");
+}
+
+#[test]
+fn rows_start_in_the_middle_of_a_chunk() {
+    let dir = TmpDir::new();
+    let log = log_data(dir.join("spill.jsonl"), ROWS);
+    assert_eq!(row_ids(&log, 37), (37..ROWS).collect::<Vec<_>>());
+}
+
+#[test]
+fn rows_are_dropped_when_the_spill_fails() {
+    let dir = TmpDir::new();
+    // The spill file can't be created inside a file
+    let blocker = dir.join("blocker");
+    fs::write(&blocker, b"").expect("blocker should be written");
+    let log = log_data(blocker.join("spill.jsonl"), ROWS);
+
+    let ids = row_ids(&log, 0);
+    assert!(!ids.is_empty() && ids.len() <= 10);
+    let first = ids[0];
+    assert!(first > 0, "spilled rows should be dropped");
+    assert_eq!(ids, (first..ROWS).collect::<Vec<_>>());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn rows_are_dropped_when_the_spill_writes_fail() {
+    let dir = TmpDir::new();
+    // The spill file is created, but every write to it fails
+    let spill_path = dir.join("spill.jsonl");
+    std::os::unix::fs::symlink("/dev/full", &spill_path).expect("symlink should be created");
+    let log = log_data(spill_path, ROWS);
+
+    let ids = row_ids(&log, 0);
+    assert!(!ids.is_empty() && ids.len() <= 10);
+    let first = ids[0];
+    assert!(first > 0, "rows that were not written should be dropped");
+    assert_eq!(ids, (first..ROWS).collect::<Vec<_>>());
+    assert_eq!(
+        rev_row_ids(&log, ROWS - 1),
+        (first..ROWS).rev().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn rows_rev_start_in_memory() {
+    let dir = TmpDir::new();
+    let log = log_data(dir.join("spill.jsonl"), ROWS);
+    assert_eq!(
+        rev_row_ids(&log, ROWS - 3),
+        (0..=ROWS - 3).rev().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        rev_row_ids(&log, RowId::MAX),
+        (0..ROWS).rev().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        page_ids(&log, ROWS - 1, Direction::Backward),
+        (vec![95, 96, 97, 98, 99], Some(94))
+    );
+}
+
+#[test]
+fn rows_rev_start_in_the_middle_of_a_chunk() {
+    let dir = TmpDir::new();
+    let log = log_data(dir.join("spill.jsonl"), INDEXED_ROWS);
+    assert_eq!(
+        rev_row_ids(&log, 1500),
+        (0..=1500).rev().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        page_ids(&log, 1500, Direction::Backward),
+        (vec![1496, 1497, 1498, 1499, 1500], Some(1495))
+    );
+}
+
+#[test]
+fn rows_rev_start_on_an_index_boundary() {
+    let dir = TmpDir::new();
+    let log = log_data(dir.join("spill.jsonl"), INDEXED_ROWS);
+    for to in [1023, 1024, 2048] {
+        assert_eq!(rev_row_ids(&log, to), (0..=to).rev().collect::<Vec<_>>());
+    }
+    assert_eq!(
+        page_ids(&log, 1024, Direction::Backward),
+        (vec![1020, 1021, 1022, 1023, 1024], Some(1019))
+    );
+    assert_eq!(
+        page_ids(&log, 1024, Direction::Forward),
+        (vec![1024, 1025, 1026, 1027, 1028], Some(1029))
+    );
+}
+
+#[test]
+fn page_stops_after_the_scan_cap() {
+    let dir = TmpDir::new();
+    let rows = MAX_PAGE_SCANNED_ROWS as RowId + 10;
+    let log = common::log_data(
+        dir.join("spill.jsonl"),
+        LogLimits::default(),
+        (0..rows).map(|i| format!("row {}", i)),
+    );
+    let only_first = |entry: &Entry| entry.message == "row 0";
+
+    let page = log
+        .page(Some(rows - 1), 5, Direction::Backward, only_first)
+        .expect("page should be readable");
+    assert!(page.rows.is_empty());
+    let next = page.next_row_id.expect("the page should stop at the cap");
+    assert_eq!(next, rows - 1 - MAX_PAGE_SCANNED_ROWS as RowId);
+
+    let page = log
+        .page(Some(next), 5, Direction::Backward, only_first)
+        .expect("page should be readable");
+    assert_eq!(page.rows.iter().map(|e| e.row_id).collect::<Vec<_>>(), [0]);
+    assert_eq!(page.next_row_id, None);
+}
@@ -0,0 +1,182 @@
+use shadps4_launcher_lib::file_format::pfs::{self, PfsFileKind};
+use shadps4_launcher_lib::file_format::pkg::{Error, PKG, PkgKeyset};
+use shadps4_launcher_lib::file_format::psf::Value;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+type ErrorCheck = fn(&Error) -> bool;
+
+fn fixture_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/pkg", name]
+        .iter()
+        .collect()
+}
+
+fn fixture(name: &str) -> File {
+    let path = fixture_path(name);
+    File::open(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", path.display(), e))
+}
+
+/// Test keys the fake.pkg fixture was made with, not the console ones
+fn keyset(derived_key3: &str, fake: &str) -> PkgKeyset {
+    let read = |name: &str| {
+        fs::read_to_string(fixture_path(&format!("keys/{}.pem", name))).expect("key should be read")
+    };
+    PkgKeyset::from_pem(&read(derived_key3), &read(fake)).expect("keys should parse")
+}
+
+#[test]
+fn header_and_entries_are_read() {
+    let mut pkg = PKG::read(fixture("valid.pkg")).expect("valid fixture should parse");
+    assert_eq!(pkg.content_id(), "UP0000-CUSA00001_00-FIXTURE000000001");
+    assert_eq!(pkg.title_id().as_deref(), Some("CUSA00001"));
+    assert_eq!(pkg.content_type(), 0x1A);
+
+    let names: Vec<_> = pkg.entries().iter().map(|e| e.name.as_deref()).collect();
+    assert_eq!(
+        names,
+        [
+            Some("entry_names"),
+            Some("param.sfo"),
+            Some("icon0.png"),
+            Some("image_key"),
+            // Only named in the entry names table
+            Some("custom.bin"),
+        ]
+    );
+
+    assert_eq!(
+        pkg.read_entry("icon0.png").expect("icon should be read"),
+        b"\x89PNG fake icon"
+    );
+    assert_eq!(
+        pkg.read_entry("custom.bin").expect("entry should be read"),
+        b"abc"
+    );
+    assert!(matches!(
+        pkg.read_entry("image_key"),
+        Err(Error::EntryEncrypted(_))
+    ));
+    assert!(matches!(
+        pkg.read_entry("pic0.png"),
+        Err(Error::EntryNotFound(_))
+    ));
+
+    let psf = pkg.param_sfo().expect("param.sfo should be read");
+    assert!(matches!(psf.get("TITLE_ID"), Some(Value::Text(_))));
+}
+
+#[test]
+fn short_content_id_has_no_title_id() {
+    let pkg = PKG::read(fixture("short_content_id.pkg")).expect("fixture should parse");
+    assert_eq!(pkg.content_id(), "UP0000-CUSA0");
+    assert_eq!(pkg.title_id(), None);
+}
+
+#[test]
+fn malformed_pkg_is_rejected() {
+    let cases: &[(&str, ErrorCheck)] = &[
+        ("truncated_header.pkg", |e| {
+            matches!(e, Error::FileTooSmall(0x800))
+        }),
+        ("bad_magic.pkg", |e| {
+            matches!(e, Error::InvalidMagicCode(0x7F504B47))
+        }),
+        ("entry_table_out_of_file.pkg", |e| {
+            matches!(e, Error::EntryTableOutOfBounds(0x10000))
+        }),
+        ("entry_out_of_file.pkg", |e| {
+            matches!(e, Error::EntryOutOfBounds(0x1200, _, 0x100000))
+        }),
+        ("truncated_body.pkg", |e| {
+            matches!(e, Error::EntryOutOfBounds(0x1000, ..))
+        }),
+    ];
+
+    for (name, check) in cases {
+        match PKG::read(fixture(&format!("malformed/{}", name))) {
+            Ok(_) => panic!("{} should not parse", name),
+            Err(e) => assert!(check(&e), "{}: unexpected error: {}", name, e),
+        }
+    }
+}
+
+#[test]
+fn fake_pkg_image_is_extracted() {
+    let mut pkg = PKG::read(fixture("fake.pkg")).expect("fixture should parse");
+    let keys = pkg
+        .keys(&keyset("derived_key3", "fake"))
+        .expect("keys should be decrypted");
+    let npbind = pkg
+        .decrypt_entry("npbind.dat", &keys)
+        .expect("npbind should be decrypted");
+    assert!(npbind.starts_with(b"npbind fixture data"), "{:?}", npbind);
+
+    let mut pfs = pkg.pfs(Some(&keys)).expect("image should open");
+    let files = pfs.files().expect("files should be listed");
+    let listed: Vec<_> = files
+        .iter()
+        .map(|f| (f.path.as_str(), f.kind, f.size))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("eboot.bin", PfsFileKind::File, 15),
+            ("sce_sys", PfsFileKind::Dir, 0),
+            // A stored block followed by a compressed one
+            ("big.bin", PfsFileKind::File, 0x10100),
+            ("sce_sys/param.sfo", PfsFileKind::File, 436),
+        ]
+    );
+
+    let mut read = |path: &str| {
+        let file = files
+            .iter()
+            .find(|f| f.path == path)
+            .expect("file is listed");
+        let mut data = Vec::new();
+        let finished = pfs
+            .copy_file(file, &mut data, |_| true)
+            .expect("file should be copied");
+        assert!(finished);
+        data
+    };
+    assert_eq!(read("eboot.bin"), b"\x7fELF fake eboot");
+    let big = read("big.bin");
+    assert_eq!(big.len(), 0x10100);
+    assert!(big.ends_with(b"tail of big.bin "));
+    assert_eq!(
+        read("sce_sys/param.sfo"),
+        fs::read(
+            [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/psf/valid.sfo"]
+                .iter()
+                .collect::<PathBuf>()
+        )
+        .expect("param.sfo fixture should be read")
+    );
+
+    // Stopping after the first block
+    let big = files
+        .iter()
+        .find(|f| f.path == "big.bin")
+        .expect("file is listed");
+    let mut data = Vec::new();
+    let finished = pfs
+        .copy_file(big, &mut data, |_| false)
+        .expect("copy should stop");
+    assert!(!finished);
+    assert_eq!(data.len(), 0x10000);
+}
+
+#[test]
+fn fake_pkg_needs_matching_keys() {
+    let mut pkg = PKG::read(fixture("fake.pkg")).expect("fixture should parse");
+    assert!(matches!(
+        pkg.pfs(None),
+        Err(Error::InvalidPfs(pfs::Error::MissingKey))
+    ));
+    assert!(matches!(
+        pkg.keys(&keyset("fake", "derived_key3")),
+        Err(Error::KeyMismatch(_))
+    ));
+}
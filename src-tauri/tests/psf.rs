@@ -0,0 +1,92 @@
+use shadps4_launcher_lib::file_format::psf::{Error, PSF, Value};
+use std::fs;
+use std::path::PathBuf;
+
+type ErrorCheck = fn(&Error) -> bool;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/psf", name]
+        .iter()
+        .collect();
+    fs::read(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", path.display(), e))
+}
+
+fn write(psf: &PSF) -> Vec<u8> {
+    let mut out = Vec::new();
+    psf.write(&mut out).expect("PSF should be writable");
+    out
+}
+
+#[test]
+fn valid_psf_round_trips() {
+    for name in ["valid.sfo", "non_sequential.sfo", "text_fills_max_len.sfo"] {
+        let data = fixture(name);
+        let psf = PSF::from_bytes(&data).expect("valid fixture should parse");
+        assert_eq!(data, write(&psf), "{} should be written back as read", name);
+    }
+}
+
+#[test]
+fn changed_values_are_written_in_place() {
+    let data = fixture("non_sequential.sfo");
+    let mut psf = PSF::from_bytes(&data).expect("valid fixture should parse");
+    psf.set("TITLE", Value::Text("Other".to_owned()));
+
+    let out = write(&psf);
+    assert_eq!(out.len(), data.len());
+    // Only the param_len of TITLE and its reserved bytes changed
+    let changed: Vec<usize> = (0..data.len()).filter(|&i| data[i] != out[i]).collect();
+    assert_eq!(changed, [0x18, 0x92, 0x93, 0x94, 0x96]);
+
+    let psf = PSF::from_bytes(&out).expect("written PSF should parse");
+    assert!(matches!(psf.get("TITLE"), Some(Value::Text(t)) if t == "Other"));
+}
+
+#[test]
+fn rebuilt_psf_keeps_unchanged_entries() {
+    let data = fixture("text_fills_max_len.sfo");
+    let mut psf = PSF::from_bytes(&data).expect("valid fixture should parse");
+    // Doesn't fit in the 6 bytes reserved for VERSION
+    psf.set("VERSION", Value::Text("01.00.01".to_owned()));
+    psf.set("CATEGORY", Value::Text("gd".to_owned()));
+
+    let out = write(&psf);
+    let written = PSF::from_bytes(&out).expect("written PSF should parse");
+    assert!(matches!(written.get("TITLE_ID"), Some(Value::Text(t)) if t == "CUSA00001"));
+    assert!(matches!(written.get("TITLE"), Some(Value::Text(t)) if t == "Full"));
+    assert!(matches!(written.get("VERSION"), Some(Value::Text(t)) if t == "01.00.01"));
+    assert!(matches!(written.get("CATEGORY"), Some(Value::Text(t)) if t == "gd"));
+    // The unchanged texts keep their bytes, with no terminator added
+    assert!(out.windows(17).any(|w| w == b"CUSA00001Full\0xyz"));
+    assert_eq!(out, write(&written));
+}
+
+#[test]
+fn malformed_psf_is_rejected() {
+    let cases: &[(&str, ErrorCheck)] = &[
+        ("empty.sfo", |e| matches!(e, Error::FileTooSmall(0))),
+        ("truncated_header.sfo", |e| matches!(e, Error::FileTooSmall(_))),
+        ("bad_magic.sfo", |e| matches!(e, Error::InvalidMagicCode(_))),
+        ("bad_version.sfo", |e| matches!(e, Error::InvalidVersion(0x200))),
+        ("huge_index_table.sfo", |e| matches!(e, Error::IndexTableOutOfBounds(_))),
+        ("table_offsets_out_of_file.sfo", |e| matches!(e, Error::InvalidTableOffsets(..))),
+        ("key_table_after_data_table.sfo", |e| matches!(e, Error::InvalidTableOffsets(..))),
+        ("key_offset_out_of_bounds.sfo", |e| matches!(e, Error::KeyOutOfBounds(_))),
+        ("unterminated_key.sfo", |e| matches!(e, Error::UnterminatedKey(_))),
+        ("param_len_over_max_len.sfo", |e| matches!(e, Error::InvalidEntryLength(..))),
+        ("data_offset_out_of_file.sfo", |e| matches!(e, Error::DataOutOfBounds(..))),
+        ("huge_param_len.sfo", |e| matches!(e, Error::DataOutOfBounds(..))),
+        ("invalid_entry_format.sfo", |e| matches!(e, Error::InvalidEntryFormat(0x0401))),
+        ("invalid_integer_size.sfo", |e| matches!(e, Error::InvalidIntEntry(2, 4))),
+        ("duplicate_key.sfo", |e| matches!(e, Error::DuplicateKey(_))),
+        ("invalid_utf8_text.sfo", |e| matches!(e, Error::InvalidTextEntryUtf(_))),
+    ];
+
+    for (name, check) in cases {
+        let data = fixture(&format!("malformed/{}", name));
+        match PSF::from_bytes(&data) {
+            Ok(_) => panic!("{} should not parse", name),
+            Err(e) => assert!(check(&e), "{}: unexpected error: {}", name, e),
+        }
+    }
+}
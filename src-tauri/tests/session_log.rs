@@ -0,0 +1,202 @@
+mod common;
+
+use common::TmpDir;
+use shadps4_launcher_lib::game_process::log::{Level, LogData, LogLimits};
+use shadps4_launcher_lib::game_process::session_log::{
+    self, SessionLog, SessionLogPolicy, file_path,
+};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Writes a log of `size` bytes, modified `age` seconds ago
+fn old_log(dir: &Path, name: &str, size: usize, age: u64) {
+    let path = dir.join(name);
+    fs::write(&path, vec![b'x'; size]).expect("log should be written");
+    File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(age)))
+        .expect("mtime should be set");
+}
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = session_log::list(dir)
+        .expect("logs should be listed")
+        .into_iter()
+        .map(|file| file.name)
+        .collect();
+    names.sort();
+    names
+}
+
+fn policy() -> SessionLogPolicy {
+    SessionLogPolicy {
+        enabled: true,
+        max_file_bytes: 1024,
+        max_files: None,
+        max_total_bytes: None,
+    }
+}
+
+#[test]
+fn prune_keeps_the_parts_of_a_session_together() {
+    let dir = TmpDir::new();
+    // Three parts of a session must count as one
+    old_log(&dir, "2020-01-03_00-00-00.log", 10, 100);
+    old_log(&dir, "2020-01-03_00-00-00.1.log", 10, 90);
+    old_log(&dir, "2020-01-03_00-00-00.2.log", 10, 80);
+    old_log(&dir, "2020-01-02_00-00-00.log", 10, 200);
+    old_log(&dir, "2020-01-01_00-00-00.log", 10, 300);
+    old_log(&dir, "2020-01-01_00-00-00.1.log", 10, 290);
+
+    let log = SessionLog::create(
+        dir.to_path_buf(),
+        SessionLogPolicy {
+            max_files: Some(3),
+            ..policy()
+        },
+    )
+    .expect("session log should be created");
+    drop(log);
+
+    let names = names(&dir);
+    assert_eq!(names.len(), 5, "{:?}", names);
+    assert_eq!(
+        names[..4],
+        [
+            "2020-01-02_00-00-00.log",
+            "2020-01-03_00-00-00.1.log",
+            "2020-01-03_00-00-00.2.log",
+            "2020-01-03_00-00-00.log",
+        ]
+    );
+}
+
+#[test]
+fn prune_removes_whole_sessions_past_the_total_size() {
+    let dir = TmpDir::new();
+    old_log(&dir, "2020-01-02_00-00-00.log", 10, 100);
+    old_log(&dir, "2020-01-02_00-00-00.1.log", 10, 90);
+    old_log(&dir, "2020-01-01_00-00-00.log", 10, 200);
+    old_log(&dir, "2020-01-01_00-00-00.1.log", 10, 190);
+
+    SessionLog::create(
+        dir.to_path_buf(),
+        SessionLogPolicy {
+            max_total_bytes: Some(30),
+            ..policy()
+        },
+    )
+    .expect("session log should be created");
+
+    let names = names(&dir);
+    assert_eq!(
+        names[..2],
+        ["2020-01-02_00-00-00.1.log", "2020-01-02_00-00-00.log"]
+    );
+    assert_eq!(names.len(), 3, "{:?}", names);
+}
+
+#[test]
+fn rotation_keeps_the_parts_of_the_current_session() {
+    let dir = TmpDir::new();
+    let log = SessionLog::create(
+        dir.to_path_buf(),
+        SessionLogPolicy {
+            // Every row goes in its own file
+            max_file_bytes: 1,
+            max_files: Some(1),
+            ..policy()
+        },
+    )
+    .expect("session log should be created");
+
+    let mut data = LogData::new(LogLimits::default(), dir.join("spill.jsonl"));
+    for i in 0..3 {
+        let (entry, _) = data.new_entry(Level::Info, "Core", format!("row {}", i));
+        log.write(i, &entry).expect("row should be written");
+    }
+    drop(log);
+
+    let files = session_log::list(&dir).expect("logs should be listed");
+    assert_eq!(files.len(), 3);
+    let stem = files[0].name.split('.').next().unwrap();
+    let mut names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            format!("{}.1.log", stem),
+            format!("{}.2.log", stem),
+            format!("{}.log", stem),
+        ]
+    );
+    for (i, name) in ["log", "1.log", "2.log"].iter().enumerate() {
+        let text = fs::read_to_string(dir.join(format!("{}.{}", stem, name)))
+            .expect("part should be readable");
+        assert!(text.contains(&format!("row {}", i)), "{}: {:?}", name, text);
+    }
+}
+
+#[test]
+fn owns_only_the_files_of_its_session() {
+    let dir = TmpDir::new();
+    old_log(&dir, "2020-01-01_00-00-00.log", 10, 100);
+    let log =
+        SessionLog::create(dir.to_path_buf(), policy()).expect("session log should be created");
+
+    let name = names(&dir)
+        .into_iter()
+        .find(|name| !name.starts_with("2020"))
+        .expect("the session log should be listed");
+    let stem = name.trim_end_matches(".log");
+    assert!(log.owns(&dir.join(&name)));
+    assert!(log.owns(&dir.join(format!("{}.1.log", stem))));
+    assert!(!log.owns(&dir.join("2020-01-01_00-00-00.log")));
+    assert!(!log.owns(&dir.join("other").join(&name)));
+}
+
+#[test]
+fn list_is_newest_first_and_only_has_logs() {
+    let dir = TmpDir::new();
+    old_log(&dir, "old.log", 1, 200);
+    old_log(&dir, "new.log", 2, 100);
+    old_log(&dir, "notes.txt", 3, 50);
+    fs::create_dir(dir.join("folder.log")).expect("folder should be created");
+
+    let files = session_log::list(&dir).expect("logs should be listed");
+    let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+    assert_eq!(names, ["new.log", "old.log"]);
+    assert_eq!(files[0].size, 2);
+
+    assert!(
+        session_log::list(&dir.join("missing"))
+            .expect("a missing folder has no logs")
+            .is_empty()
+    );
+}
+
+#[test]
+fn file_path_refuses_names_outside_the_folder() {
+    let dir = Path::new("/logs/GAME");
+    assert_eq!(
+        file_path(dir, "2020-01-01_00-00-00.1.log").expect("name should be valid"),
+        dir.join("2020-01-01_00-00-00.1.log")
+    );
+    for name in [
+        "",
+        "..",
+        "../other.log",
+        "sub/file.log",
+        "/etc/passwd.log",
+        "file.txt",
+        "file",
+    ] {
+        assert!(
+            file_path(dir, name).is_err(),
+            "{:?} should be refused",
+            name
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use shadps4_launcher_lib::file_format::trp::{
+    Error, EsfmKey, Grade, TRP, TrophyConfig, parse_trophy_key, read_np_comm_id,
+};
+use std::fs::File;
+use std::path::PathBuf;
+
+/// The fixtures are encrypted with this key instead of the console one
+const TROPHY_KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+fn fixture(name: &str) -> File {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/trp", name]
+        .iter()
+        .collect();
+    File::open(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", path.display(), e))
+}
+
+fn esfm_key() -> EsfmKey {
+    let trophy_key = parse_trophy_key(TROPHY_KEY).expect("test key should parse");
+    let np_comm_id = read_np_comm_id(fixture("npbind.dat"), 0).expect("npbind should be read");
+    assert_eq!(&np_comm_id[..12], b"NPWR00000_00");
+    EsfmKey::new(&trophy_key, &np_comm_id)
+}
+
+fn check_config(config: &TrophyConfig) {
+    assert_eq!(config.np_comm_id.as_deref(), Some("NPWR00000_00"));
+    assert_eq!(config.title_name.as_deref(), Some("Fixture"));
+
+    assert_eq!(config.groups.len(), 1);
+    assert_eq!(config.groups[0].id, 1);
+
+    assert_eq!(config.trophies.len(), 2);
+    let platinum = &config.trophies[0];
+    assert_eq!(platinum.grade, Grade::Platinum);
+    assert_eq!(platinum.name.as_deref(), Some("All trophies"));
+    assert_eq!(platinum.icon.as_deref(), Some("TROP000.PNG"));
+
+    let empty = &config.trophies[1];
+    assert_eq!(empty.id, 1);
+    assert!(empty.hidden);
+    assert_eq!(empty.grade, Grade::Bronze);
+    assert_eq!(empty.group_id, Some(1));
+    assert_eq!(empty.name, None);
+}
+
+#[test]
+fn plain_config_is_read_with_self_closing_elements() {
+    let mut trp = TRP::read(fixture("plain.trp")).expect("fixture should parse");
+    let config = trp
+        .trophy_config(None)
+        .expect("config should be valid")
+        .expect("config should be found");
+    check_config(&config);
+}
+
+#[test]
+fn encrypted_config_needs_the_key() {
+    let mut trp = TRP::read(fixture("encrypted.trp")).expect("fixture should parse");
+    assert!(
+        trp.trophy_config(None)
+            .expect("missing config is not an error")
+            .is_none()
+    );
+    assert!(matches!(
+        trp.read_entry("TROP.ESFM"),
+        Err(Error::EntryEncrypted(_))
+    ));
+
+    let config = trp
+        .trophy_config(Some(&esfm_key()))
+        .expect("config should decrypt")
+        .expect("config should be found");
+    check_config(&config);
+}
+
+#[test]
+fn invalid_trophy_key_is_rejected() {
+    for key in [
+        "",
+        "0011",
+        "zz0102030405060708090a0b0c0d0e0f",
+        "é0102030405060708090a0b0c0d0e0f",
+    ] {
+        assert!(
+            matches!(parse_trophy_key(key), Err(Error::InvalidTrophyKey)),
+            "{:?} should be rejected",
+            key
+        );
+    }
+}
@@ -11,7 +11,7 @@ import {
     type Theme,
 } from "@glideapps/glide-data-grid";
 import { format } from "date-fns";
-import { useAtomValue, useSetAtom } from "jotai";
+import { useAtomValue } from "jotai";
 import {
     useCallback,
     useEffect,
@@ -20,7 +20,13 @@ import {
     useState,
 } from "react";
 import { useThemeStyle } from "@/lib/hooks/useThemeStyle";
-import { type LogEntry, LogLevel } from "@/lib/native/game-process";
+import {
+    type GameEvent,
+    type GameProcess,
+    type LogEntry,
+    LogLevel,
+    type LogPage,
+} from "@/lib/native/game-process";
 import type { GameProcessState } from "@/store/running-games";
 
 const theme = {
@@ -39,6 +45,12 @@ const darkTheme = {
     textDark: "#d1d5db",
 } satisfies Partial<Theme>;
 
+/** Background of the rows matching the search */
+const matchBg = {
+    light: "#fef9c3",
+    dark: "#713f12",
+};
+
 type LevelTheme = {
     bg: string;
     text: string;
@@ -110,6 +122,14 @@ const levelThemes = {
     dark: Record<LogLevel, LevelTheme>;
 };
 
+const PAGE_SIZE = 1000;
+/** Older rows are fetched when the view gets this close to the top */
+const LOAD_MORE_THRESHOLD = 100;
+/** Search pages followed before giving up on highlighting older rows */
+const MAX_SEARCH_PAGES = 50;
+/** Empty log pages followed before waiting for the next scroll */
+const MAX_EMPTY_PAGES = 50;
+
 const baseColumns: GridColumn[] = [
     {
         title: "Time",
@@ -137,13 +157,45 @@ type Props = {
     runningGame: GameProcessState;
     levelFilter?: LogLevel[] | undefined;
     classFilter?: string[] | undefined;
+    /** Rows whose message contains it are highlighted, ignoring case */
+    search?: string | undefined;
 };
 
-export function LogList({ runningGame, levelFilter, classFilter }: Props) {
+/** A page of older rows, following the empty pages returned when the filters skip many rows */
+async function getOlderPage(
+    process: GameProcess,
+    filters: Pick<Props, "levelFilter" | "classFilter">,
+    fromRowId: number | null,
+): Promise<LogPage> {
+    let page: LogPage = { rows: [], nextRowId: fromRowId };
+    for (let i = 0; i < MAX_EMPTY_PAGES; i++) {
+        page = await process.getLog({
+            level: filters.levelFilter,
+            logClass: filters.classFilter,
+            fromRowId: page.nextRowId,
+            limit: PAGE_SIZE,
+            direction: "backward",
+        });
+        if (page.rows.length > 0 || page.nextRowId == null) {
+            break;
+        }
+    }
+    return page;
+}
+
+function matchesSearch(message: string, search: string) {
+    return message.toLowerCase().includes(search.toLowerCase());
+}
+
+export function LogList({
+    runningGame,
+    levelFilter,
+    classFilter,
+    search,
+}: Props) {
     "use no memo";
 
     const isDark = useThemeStyle() === "dark";
-    const setLogCallback = useSetAtom(runningGame.log.atomCallback);
     const process = useAtomValue(runningGame.atomProcess);
 
     const [columns, setColumns] = useState(() =>
@@ -154,35 +206,148 @@ export function LogList({ runningGame, levelFilter, classFilter }: Props) {
     const dataGridRef = useRef<DataEditorRef | null>(null);
     const prevVisibleRegion = useRef<Rectangle | null>(null);
     const isScrollFollowing = useRef(true);
+    const olderRowId = useRef<number | null>(null);
+    const isLoadingOlder = useRef(false);
+    const matches = useRef(new Set<number>());
+    const [matchVersion, setMatchVersion] = useState(0);
+    const searchRef = useRef(search);
+    searchRef.current = search;
 
+    // The last page is loaded first, then the rows logged after it are followed
     useEffect(() => {
-        process
-            .getLog({
-                level: levelFilter,
-                logClass: classFilter,
-            })
-            .then((log) => {
-                setRowData(log);
-                setRowCount(log.length);
-            });
-    }, [process, levelFilter, classFilter]);
+        olderRowId.current = null;
+        let rows: LogEntry[] = [];
+        let lastRowId = -1;
+        let unsubscribe: (() => Promise<void>) | null = null;
+        let cancelled = false;
 
-    useEffect(() => {
-        const c = (log: LogEntry) => {
-            if (levelFilter && !levelFilter.includes(log.level)) {
+        const onEvent = (ev: GameEvent) => {
+            if (ev.event !== "log" || ev.rowId <= lastRowId) {
                 return;
             }
-            if (classFilter && !classFilter.includes(log.class)) {
+            lastRowId = ev.rowId;
+            if (levelFilter && !levelFilter.includes(ev.level)) {
                 return;
             }
-            rowData.push(log);
-            setRowCount(rowData.length);
+            if (classFilter && !classFilter.includes(ev.class)) {
+                return;
+            }
+            const { event: _, ...entry } = ev;
+            const search = searchRef.current;
+            if (search && matchesSearch(entry.message, search)) {
+                matches.current.add(entry.rowId);
+            }
+            rows.push(entry);
+            setRowCount(rows.length);
         };
-        setLogCallback((prev) => [...prev, c]);
+
+        (async () => {
+            const page = await getOlderPage(
+                process,
+                { levelFilter, classFilter },
+                null,
+            );
+            if (cancelled) {
+                return;
+            }
+            olderRowId.current = page.nextRowId;
+            rows = page.rows;
+            lastRowId = rows.at(-1)?.rowId ?? -1;
+            setRowData(rows);
+            setRowCount(rows.length);
+
+            const unsub = await process.subscribe(onEvent, lastRowId + 1);
+            if (cancelled) {
+                await unsub();
+                return;
+            }
+            unsubscribe = unsub;
+        })().catch((e: unknown) => {
+            console.error("could not follow the log", e);
+        });
+
         return () => {
-            setLogCallback((prev) => prev.filter((e) => e !== c));
+            cancelled = true;
+            unsubscribe?.().catch((e: unknown) => {
+                console.error("could not unsubscribe from the log", e);
+            });
+        };
+    }, [process, levelFilter, classFilter]);
+
+    // Rows logged from now on are matched in `onEvent`
+    useEffect(() => {
+        matches.current = new Set();
+        setMatchVersion((v) => v + 1);
+        if (!search) {
+            return;
+        }
+        let cancelled = false;
+
+        (async () => {
+            let cursor: number | null = null;
+            for (let i = 0; i < MAX_SEARCH_PAGES; i++) {
+                const result = await process.searchLog({
+                    pattern: search,
+                    caseInsensitive: true,
+                    level: levelFilter,
+                    logClass: classFilter,
+                    cursor,
+                    limit: PAGE_SIZE,
+                });
+                if (cancelled) {
+                    return;
+                }
+                for (const match of result.matches) {
+                    matches.current.add(match.rowId);
+                }
+                setMatchVersion((v) => v + 1);
+                cursor = result.nextCursor;
+                if (cursor == null) {
+                    return;
+                }
+            }
+        })().catch((e: unknown) => {
+            console.error("could not search the log", e);
+        });
+
+        return () => {
+            cancelled = true;
         };
-    }, [levelFilter, classFilter, rowData, setLogCallback]);
+    }, [process, search, levelFilter, classFilter]);
+
+    const loadOlder = useCallback(
+        async (visibleRow: number) => {
+            const fromRowId = olderRowId.current;
+            if (isLoadingOlder.current || fromRowId == null) {
+                return;
+            }
+            isLoadingOlder.current = true;
+            try {
+                const page = await getOlderPage(
+                    process,
+                    { levelFilter, classFilter },
+                    fromRowId,
+                );
+                if (olderRowId.current !== fromRowId) {
+                    return; // filters changed
+                }
+                olderRowId.current = page.nextRowId;
+                rowData.unshift(...page.rows);
+                setRowCount(rowData.length);
+                dataGridRef.current?.scrollTo(
+                    0,
+                    visibleRow + page.rows.length,
+                    "vertical",
+                    undefined,
+                    undefined,
+                    { vAlign: "start" },
+                );
+            } finally {
+                isLoadingOlder.current = false;
+            }
+        },
+        [process, levelFilter, classFilter, rowData],
+    );
 
     useEffect(() => {
         return () => {
@@ -219,6 +384,9 @@ export function LogList({ runningGame, levelFilter, classFilter }: Props) {
             }
 
             const themeOverride: Partial<Theme> = {};
+            if (matches.current.has(entry.rowId)) {
+                themeOverride.bgCell = isDark ? matchBg.dark : matchBg.light;
+            }
             const style = (isDark ? levelThemes.dark : levelThemes.light)[
                 entry.level
             ];
@@ -269,7 +437,8 @@ export function LogList({ runningGame, levelFilter, classFilter }: Props) {
                 throw new Error("Unexpected cell kind");
             }
         },
-        [rowData, isDark],
+        // matchVersion redraws the cells when the matches change
+        [rowData, isDark, matchVersion],
     );
 
     const getCellForSelection = useCallback(
@@ -314,8 +483,11 @@ export function LogList({ runningGame, levelFilter, classFilter }: Props) {
                 isScrollFollowing.current = true;
             }
             prevVisibleRegion.current = range;
+            if (range.y < LOAD_MORE_THRESHOLD) {
+                void loadOlder(range.y);
+            }
         },
-        [rowCount],
+        [rowCount, loadOlder],
     );
 
     const onColumnResize = useCallback(
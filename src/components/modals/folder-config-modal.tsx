@@ -5,7 +5,13 @@ import { useEffect, useState } from "react";
 import { toast } from "sonner";
 import { useNavigator } from "@/lib/hooks/useNavigator";
 import { stringifyError } from "@/lib/utils/error";
-import { atomEmuUserPath, atomGamesPath } from "@/store/paths";
+import { atomLogLimits, DEFAULT_LOG_LIMITS } from "@/store/log-settings";
+import type { PkgKeyFiles } from "@/lib/native/pkg";
+import {
+    atomEmuUserPath,
+    atomGamesPath,
+    atomPkgKeyFiles,
+} from "@/store/paths";
 import { Button } from "../ui/button";
 import { Checkbox } from "../ui/checkbox";
 import { Dialog, DialogContent, DialogTitle } from "../ui/dialog";
@@ -134,6 +140,139 @@ function PathConfig() {
     );
 }
 
+const MIB = 1024 * 1024;
+
+/** Empty is unlimited */
+function parseLimit(value: string, unit = 1): number | null {
+    const n = Number.parseInt(value, 10);
+    return Number.isNaN(n) || n <= 0 ? null : n * unit;
+}
+
+function LogConfig() {
+    const [limits, setLimits] = useAtom(atomLogLimits);
+
+    const [inputRows, setInputRows] = useState("");
+    const [inputMib, setInputMib] = useState("");
+
+    useEffect(() => {
+        setInputRows(limits.maxRows?.toString() ?? "");
+        setInputMib(
+            limits.maxBytes != null
+                ? Math.round(limits.maxBytes / MIB).toString()
+                : "",
+        );
+    }, [limits]);
+
+    const save = () => {
+        setLimits({
+            maxRows: parseLimit(inputRows),
+            maxBytes: parseLimit(inputMib, MIB),
+        });
+    };
+
+    return (
+        <div className="flex w-full flex-col gap-2 px-4 pb-4">
+            <Tooltip delayDuration={1}>
+                <TooltipTrigger asChild>
+                    <Label>Game log kept in memory</Label>
+                </TooltipTrigger>
+                <TooltipContent>
+                    Older rows are moved to disk. Applies to the games started
+                    from now on, leave empty for no limit.
+                </TooltipContent>
+            </Tooltip>
+            <div className="grid w-full max-w-sm grid-cols-2 gap-2">
+                <Input
+                    min={1}
+                    onBlur={save}
+                    onChange={(e) => setInputRows(e.target.value)}
+                    placeholder="Rows"
+                    type="number"
+                    value={inputRows}
+                />
+                <Input
+                    min={1}
+                    onBlur={save}
+                    onChange={(e) => setInputMib(e.target.value)}
+                    placeholder="MiB"
+                    type="number"
+                    value={inputMib}
+                />
+            </div>
+            <Button
+                className="max-w-sm"
+                onClick={() => setLimits(DEFAULT_LOG_LIMITS)}
+                variant="secondary"
+            >
+                Restore defaults
+            </Button>
+        </div>
+    );
+}
+
+const PKG_KEYS: { key: keyof PkgKeyFiles; label: string }[] = [
+    { key: "derivedKey3", label: "PKG derived key 3" },
+    { key: "fake", label: "Fake PKG key" },
+];
+
+function PkgKeysConfig() {
+    const [keyFiles, setKeyFiles] = useAtom(atomPkgKeyFiles);
+
+    const setKeyFile = (key: keyof PkgKeyFiles, path: string) => {
+        const files = { derivedKey3: "", fake: "", ...keyFiles, [key]: path };
+        setKeyFiles(files.derivedKey3 || files.fake ? files : null);
+    };
+
+    const askKeyFile = (key: keyof PkgKeyFiles) => {
+        openDialog({
+            filters: [{ name: "PEM key", extensions: ["pem"] }],
+        })
+            .then((e) => {
+                if (e) {
+                    setKeyFile(key, e);
+                }
+            })
+            .catch((e) => {
+                toast.error(stringifyError(e));
+                console.error(e);
+            });
+    };
+
+    return (
+        <div className="flex w-full flex-col gap-2 px-4 pb-4">
+            <Tooltip delayDuration={1}>
+                <TooltipTrigger asChild>
+                    <Label>Package keys</Label>
+                </TooltipTrigger>
+                <TooltipContent>
+                    RSA keys in PEM format, needed to install fake-signed
+                    packages. They are not included with the launcher.
+                </TooltipContent>
+            </Tooltip>
+            {PKG_KEYS.map(({ key, label }) => (
+                <div
+                    className="grid w-full max-w-sm grid-cols-4 items-center"
+                    key={key}
+                >
+                    <Input
+                        className="col-span-3 rounded-r-none"
+                        onChange={(e) => setKeyFile(key, e.target.value)}
+                        placeholder={label}
+                        type="text"
+                        value={keyFiles?.[key] ?? ""}
+                    />
+                    <Button
+                        className="rounded-l-none"
+                        onClick={() => askKeyFile(key)}
+                    >
+                        Browse
+                    </Button>
+                </div>
+            ))}
+        </div>
+    );
+}
+
 export function FolderConfigModal() {
     const { popModal } = useNavigator();
 
@@ -142,6 +281,8 @@ export function FolderConfigModal() {
             <DialogTitle className="hidden">Configuration</DialogTitle>
             <DialogContent aria-describedby={undefined}>
                 <PathConfig />
+                <LogConfig />
+                <PkgKeysConfig />
             </DialogContent>
         </Dialog>
     );
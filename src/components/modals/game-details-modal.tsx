@@ -1,5 +1,11 @@
+import { format } from "date-fns";
+import { useAtomValue } from "jotai";
+import { StarIcon } from "lucide-react";
 import * as motion from "motion/react-client";
+import { useState } from "react";
+import { toast } from "sonner";
 import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
 import {
     Dialog,
     DialogContent,
@@ -7,6 +13,7 @@ import {
     DialogHeader,
     DialogTitle,
 } from "@/components/ui/dialog";
+import { Input } from "@/components/ui/input";
 import {
     Table,
     TableBody,
@@ -21,7 +28,14 @@ import {
 } from "@/lib/context/gamepad-nav-field";
 import { useNavigator } from "@/lib/hooks/useNavigator";
 import type { PSFEntry } from "@/lib/native/psf";
+import { stringifyError } from "@/lib/utils/error";
 import type { GameEntry } from "@/store/db";
+import {
+    atomGameLibrary,
+    setGameFavourite,
+    setGameNotes,
+    setGameTags,
+} from "@/store/game-library";
 import { GameBoxCover } from "../game-cover";
 
 function Entry({ value }: { value: PSFEntry }) {
@@ -44,15 +58,54 @@ function Entry({ value }: { value: PSFEntry }) {
     return <span className="text-red-500 italic">[Unknown Format]</span>;
 }
 
+function formatPlayTime(seconds: number): string {
+    const hours = Math.floor(seconds / 3600);
+    const minutes = Math.floor((seconds % 3600) / 60);
+    return hours > 0 ? `${hours}h ${minutes}m` : `${minutes}m`;
+}
+
+function parseTags(value: string): string[] {
+    return value
+        .split(",")
+        .map((t) => t.trim())
+        .filter((t) => t !== "");
+}
+
 type Props = {
     gameData: GameEntry;
 };
 
-export function GameDetailsModal({ gameData }: Props) {
+export function GameDetailsModal({ gameData: initialGameData }: Props) {
     const { popModal } = useNavigator();
 
+    // Follows the changes made here and the stats updated when the game exits
+    const gameData =
+        useAtomValue(atomGameLibrary).find(
+            (e) => e.id === initialGameData.id,
+        ) ?? initialGameData;
+    const [notes, setNotes] = useState(gameData.notes ?? "");
+    const [tags, setTags] = useState(gameData.tags.join(", "));
+
     const sfo = gameData.sfo;
 
+    const onError = (e: unknown) => {
+        toast.error(`Could not save the game details: ${stringifyError(e)}`);
+    };
+
+    const saveNotes = () => {
+        const value = notes.trim() === "" ? null : notes;
+        if (value !== gameData.notes) {
+            setGameNotes(gameData.id, value).catch(onError);
+        }
+    };
+
+    const saveTags = () => {
+        const value = parseTags(tags);
+        if (value.join(",") !== gameData.tags.join(",")) {
+            setGameTags(gameData.id, value).catch(onError);
+        }
+    };
+
     const onButtonPress = (btn: NavButton) => {
         if (btn === "back") {
             popModal();
@@ -72,8 +125,31 @@ export function GameDetailsModal({ gameData }: Props) {
                     className="flex max-h-[90vh] max-w-xl flex-col md:max-w-4xl"
                 >
                     <DialogHeader className="flex-shrink-0">
-                        <DialogTitle className="text-2xl">
+                        <DialogTitle className="flex items-center gap-2 text-2xl">
                             {gameData.title}
+                            <Button
+                                aria-label={
+                                    gameData.favourite
+                                        ? "Remove from favourites"
+                                        : "Add to favourites"
+                                }
+                                onClick={() =>
+                                    setGameFavourite(
+                                        gameData.id,
+                                        !gameData.favourite,
+                                    ).catch(onError)
+                                }
+                                size="icon"
+                                variant="ghost"
+                            >
+                                <StarIcon
+                                    className={
+                                        gameData.favourite
+                                            ? "fill-yellow-400 text-yellow-400"
+                                            : ""
+                                    }
+                                />
+                            </Button>
                         </DialogTitle>
                         <DialogDescription id="game-details-description">
                             {gameData.cusa}
@@ -107,6 +183,52 @@ export function GameDetailsModal({ gameData }: Props) {
                                         {gameData.fw_version}
                                     </Badge>
                                 </div>
+                                <div className="space-y-1">
+                                    <h3 className="font-medium text-muted-foreground text-sm">
+                                        Play time
+                                    </h3>
+                                    <p>
+                                        {formatPlayTime(gameData.play_time)}
+                                        {" · "}
+                                        {gameData.launch_count} launches
+                                        {gameData.last_launched != null &&
+                                            ` · last played ${format(
+                                                gameData.last_launched * 1000,
+                                                "yyyy-MM-dd HH:mm",
+                                            )}`}
+                                    </p>
+                                </div>
+                                <div className="space-y-1">
+                                    <h3 className="font-medium text-muted-foreground text-sm">
+                                        Tags
+                                    </h3>
+                                    <Input
+                                        onBlur={saveTags}
+                                        onChange={(e) =>
+                                            setTags(e.target.value)
+                                        }
+                                        onKeyDown={(e) => {
+                                            if (e.key === "Enter") {
+                                                saveTags();
+                                            }
+                                        }}
+                                        placeholder="Comma separated"
+                                        value={tags}
+                                    />
+                                </div>
+                                <div className="space-y-1">
+                                    <h3 className="font-medium text-muted-foreground text-sm">
+                                        Notes
+                                    </h3>
+                                    <textarea
+                                        className="min-h-20 w-full rounded-md border border-input bg-transparent px-3 py-2 text-sm shadow-xs placeholder:text-muted-foreground focus-visible:outline-hidden focus-visible:ring-1 focus-visible:ring-ring"
+                                        onBlur={saveNotes}
+                                        onChange={(e) =>
+                                            setNotes(e.target.value)
+                                        }
+                                        value={notes}
+                                    />
+                                </div>
 
                                 {sfo?.entries && (
                                     <>
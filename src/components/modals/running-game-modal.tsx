@@ -8,6 +8,7 @@ import {
     MaximizeIcon,
     PauseIcon,
     SaveIcon,
+    SearchIcon,
     Trash2Icon,
     Volume2Icon,
     XIcon,
@@ -18,9 +19,10 @@ import {
     GamepadNavField,
     type NavButton,
 } from "@/lib/context/gamepad-nav-field";
+import { useDebounce } from "@/lib/hooks/useDebounce";
 import { useGameCover } from "@/lib/hooks/useGameCover";
 import { useNavigator } from "@/lib/hooks/useNavigator";
-import { LogLevel } from "@/lib/native/game-process";
+import { type LogExportOptions, LogLevel } from "@/lib/native/game-process";
 import { stringifyError } from "@/lib/utils/error";
 import { capitalize } from "@/lib/utils/strings";
 import { cn } from "@/lib/utils/ui";
@@ -59,6 +61,7 @@ import {
     DropdownMenuSubTrigger,
     DropdownMenuTrigger,
 } from "../ui/dropdown-menu";
+import { Input } from "../ui/input";
 import { Navigable } from "../ui/navigable";
 import { Skeleton } from "../ui/skeleton";
 import { Tooltip, TooltipContent, TooltipTrigger } from "../ui/tooltip";
@@ -82,13 +85,16 @@ export function RunningGameModal({
     const availableClassLog = useAtomValue(log.atomClassList);
     const [logLevelFilter, setLogLevelFilter] = useState<LogLevel[]>([]);
     const [logClassFilter, setLogClassFilter] = useState<string[]>([]);
+    const [gzipLog, setGzipLog] = useState(false);
+    const [logSearch, setLogSearch] = useState("");
+    const debouncedLogSearch = useDebounce(logSearch, 200);
 
     const close = () => {
         popModal();
     };
 
     const kill = () => {
-        process.kill();
+        process.stop();
     };
 
     const trash = () => {
@@ -103,12 +109,13 @@ export function RunningGameModal({
         }
     };
 
-    const saveLog = async () => {
+    const saveLog = async (options: LogExportOptions = {}) => {
         try {
             await process.saveLog({
                 defaultName: `shadps4-log-${game.cusa}-${format(new Date(), "yyyy-MM-dd_HH-mm")}.txt`,
                 level: logLevelFilter,
                 logClass: logClassFilter,
+                options: { ...options, gzip: gzipLog },
             });
             toast.success("Log saved");
         } catch (e: unknown) {
@@ -129,6 +136,33 @@ export function RunningGameModal({
         );
     }
 
+    const saveMenuContent = (
+        <DropdownMenuContent>
+            <DropdownMenuItem onClick={() => saveLog()}>Text</DropdownMenuItem>
+            <DropdownMenuItem onClick={() => saveLog({ format: "raw" })}>
+                Raw
+            </DropdownMenuItem>
+            <DropdownMenuItem
+                onClick={() => saveLog({ format: "raw", keepAnsi: true })}
+            >
+                Raw with colors
+            </DropdownMenuItem>
+            <DropdownMenuItem onClick={() => saveLog({ format: "jsonLines" })}>
+                JSON Lines
+            </DropdownMenuItem>
+            <DropdownMenuItem onClick={() => saveLog({ format: "csv" })}>
+                CSV
+            </DropdownMenuItem>
+            <DropdownMenuCheckboxItem
+                checked={gzipLog}
+                onCheckedChange={setGzipLog}
+                onSelect={(e) => e.preventDefault()}
+            >
+                Compress (gzip)
+            </DropdownMenuCheckboxItem>
+        </DropdownMenuContent>
+    );
+
     const filterMenuContent = (
         <DropdownMenuContent>
             <Tooltip>
@@ -283,22 +317,39 @@ export function RunningGameModal({
                                 <h3 className="font-medium text-sm">
                                     Output Log
                                 </h3>
+                                <div className="relative flex">
+                                    <SearchIcon className="absolute top-2 left-2 size-4 text-muted-foreground" />
+                                    <Navigable>
+                                        <Input
+                                            className="h-8 pl-8"
+                                            onChange={(e) =>
+                                                setLogSearch(e.target.value)
+                                            }
+                                            placeholder="Highlight..."
+                                            type="search"
+                                            value={logSearch}
+                                        />
+                                    </Navigable>
+                                </div>
                             </div>
                             <div>
-                                <Tooltip>
-                                    <TooltipTrigger asChild>
-                                        <Badge
-                                            className="mr-4"
-                                            onClick={saveLog}
-                                        >
-                                            <SaveIcon size={12} /> Save Log
-                                        </Badge>
-                                    </TooltipTrigger>
-                                    <TooltipContent>
-                                        The output will contain only the
-                                        filtered data
-                                    </TooltipContent>
-                                </Tooltip>
+                                <DropdownMenu>
+                                    <Tooltip>
+                                        <TooltipTrigger asChild>
+                                            <DropdownMenuTrigger asChild>
+                                                <Badge className="mr-4">
+                                                    <SaveIcon size={12} /> Save
+                                                    Log
+                                                </Badge>
+                                            </DropdownMenuTrigger>
+                                        </TooltipTrigger>
+                                        <TooltipContent>
+                                            The output will contain only the
+                                            filtered data
+                                        </TooltipContent>
+                                    </Tooltip>
+                                    {saveMenuContent}
+                                </DropdownMenu>
                                 <Badge
                                     className="text-xs"
                                     variant={
@@ -324,6 +375,7 @@ export function RunningGameModal({
                                     : undefined
                             }
                             runningGame={runningGame}
+                            search={debouncedLogSearch || undefined}
                         />
                     </div>
 
@@ -0,0 +1,94 @@
+import { toast } from "sonner";
+import { cancelPkgInstall, installPkg } from "@/lib/native/pkg";
+import { stringifyError } from "@/lib/utils/error";
+import type { JotaiStore } from "@/store";
+import { atomGamesPath, atomPkgKeyFiles } from "@/store/paths";
+
+/** Installs a package into the games folder, showing the progress in a toast */
+export async function installPackage(
+    store: JotaiStore,
+    pkgPath: string,
+    overwrite = false,
+) {
+    const gamesPath = store.get(atomGamesPath);
+    if (!gamesPath) {
+        toast.warning("No games folder configured");
+        return;
+    }
+
+    const keyFiles = store.get(atomPkgKeyFiles);
+    const keys = keyFiles?.derivedKey3 && keyFiles.fake ? keyFiles : null;
+
+    const cancel = {
+        label: "Cancel",
+        onClick: () => {
+            cancelPkgInstall(pkgPath).catch((e) =>
+                console.error("Couldn't cancel the install:", e),
+            );
+        },
+    };
+    const id = toast.loading("Installing package", { action: cancel });
+    let percent = -1;
+
+    try {
+        await installPkg(
+            pkgPath,
+            gamesPath,
+            (ev) => {
+                switch (ev.event) {
+                    case "started":
+                        if (ev.resumed) {
+                            toast.loading("Resuming package install", {
+                                id,
+                                action: cancel,
+                            });
+                        }
+                        break;
+                    case "progress": {
+                        const p = Math.floor(
+                            (ev.doneBytes * 100) / Math.max(ev.totalBytes, 1),
+                        );
+                        if (p !== percent) {
+                            percent = p;
+                            toast.loading(`Installing package: ${p}%`, {
+                                id,
+                                action: cancel,
+                            });
+                        }
+                        break;
+                    }
+                    case "finished":
+                        toast.success(`Package installed at ${ev.path}`, {
+                            id,
+                            action: undefined,
+                        });
+                        break;
+                    case "cancelled":
+                        toast.info(
+                            "Install cancelled, it continues from where it stopped next time",
+                            { id, action: undefined },
+                        );
+                        break;
+                }
+            },
+            { overwrite, keys },
+        );
+    } catch (e: unknown) {
+        const msg = stringifyError(e);
+        if (!overwrite && msg.includes("already installed")) {
+            toast.warning("The game is already installed", {
+                id,
+                action: {
+                    label: "Replace",
+                    onClick: () => void installPackage(store, pkgPath, true),
+                },
+            });
+            return;
+        }
+        toast.error(`Couldn't install the package: ${msg}`, {
+            id,
+            action: undefined,
+        });
+        console.error("Couldn't install the package:", e);
+    }
+}
@@ -2,6 +2,8 @@ import { convertFileSrc } from "@tauri-apps/api/core";
 import { join } from "@tauri-apps/api/path";
 import { exists } from "@tauri-apps/plugin-fs";
 import { useEffect, useState } from "react";
+import { isPackagePath } from "@/lib/native/library";
+import { readPkgEntry } from "@/lib/native/pkg";
 import type { GameEntry } from "@/store/db";
 
 const globalGameCoverCache = new WeakMap<GameEntry, string | null>();
@@ -18,10 +20,21 @@ export function useGameCover(game: GameEntry): [boolean, string | null] {
             return;
         }
         (async () => {
-            const path = await join(game.path, "sce_sys", "icon0.png");
             let value: string | null = null;
-            if (await exists(path)) {
-                value = convertFileSrc(path);
+            if (isPackagePath(game.path)) {
+                try {
+                    const icon = await readPkgEntry(game.path, "icon0.png");
+                    value = URL.createObjectURL(
+                        new Blob([icon], { type: "image/png" }),
+                    );
+                } catch (e: unknown) {
+                    console.warn(`could not read icon of ${game.path}`, e);
+                }
+            } else {
+                const path = await join(game.path, "sce_sys", "icon0.png");
+                if (await exists(path)) {
+                    value = convertFileSrc(path);
+                }
             }
             globalGameCoverCache.set(game, value);
             setCover(value);
@@ -40,28 +40,181 @@ export type LogEntry = {
 export type GameEvent =
     | ({ event: "log" } & LogEntry)
     | { event: "addLogClass"; value: string }
-    | { event: "gameExit"; status: number }
+    | ({ event: "gameExit" } & ExitInfo)
     | { event: "iOError"; err: string }
-    | { event: "ipcLine"; value: string };
+    | { event: "ipcEnabled" }
+    | { event: "capabilities"; capabilities: string[] }
+    | { event: "restartRequested"; args: string[] }
+    | { event: "restarted"; oldPid: number; newPid: number }
+    | { event: "ipcError"; err: string }
+    /** The listener fell behind and missed events */
+    | { event: "lagged"; skipped: number };
+
+/** The last step taken to stop the emulator, `exited` if it exited on its own */
+export type ExitStage = "exited" | "ipc" | "terminate" | "kill";
+
+export type ExitInfo = {
+    /** Missing if the process was ended by a signal */
+    status: number | null;
+    signal: number | null;
+    stage: ExitStage;
+};
+
+export type LogPage = {
+    /** In ascending order */
+    rows: LogEntry[];
+    /**
+     * Where the next page in the same direction starts, `null` at the end of the log.
+     * The page may be short, or empty, when the filters skipped most of the rows read
+     */
+    nextRowId: number | null;
+};
+
+export type LogSearchQuery = {
+    /** Matched against the message. Empty matches every row */
+    pattern?: string;
+    mode?: "substring" | "regex";
+    caseInsensitive?: boolean;
+    level?: LogLevel[];
+    logClass?: string[];
+    /** Unix timestamps in seconds */
+    fromTime?: number | null;
+    toTime?: number | null;
+    fromRowId?: number | null;
+    /** Exclusive */
+    toRowId?: number | null;
+    /** The `nextCursor` of the previous page */
+    cursor?: number | null;
+    limit?: number;
+};
+
+export type LogSearchResult = {
+    /** `ranges` are `[start, end)` string indexes of each hit in the message */
+    matches: (LogEntry & { ranges: [number, number][] })[];
+    nextCursor: number | null;
+};
+
+export type LogClass = {
+    name: string;
+    /** Rows of this class in the whole log */
+    rows: number;
+};
+
+export type LogExportOptions = {
+    /** `text` is `HH:MM:SS [class] <level> message`, `raw` the lines as the emulator printed them */
+    format?: "text" | "raw" | "jsonLines" | "csv";
+    /** Raw lines keep their ANSI color codes */
+    keepAnsi?: boolean;
+    gzip?: boolean;
+};
+
+export type MemoryPatch = {
+    modName: string;
+    offset: string;
+    value: string;
+    target?: string;
+    size?: string;
+    isOffset: boolean;
+    littleEndian?: boolean;
+    patchMask?: number;
+    patchSize?: number;
+};
+
+export type IpcCommand =
+    | { type: "run" }
+    | { type: "start" }
+    | { type: "stop" };
+
+/** Launcher-assigned handle, stable across emulator restarts */
+export type SessionId = number;
+
+export type SubscriptionId = number;
+
+export type SessionInfo = {
+    id: SessionId;
+    /** Current OS pid, changes when the emulator restarts */
+    pid: number;
+    exe: string;
+    wd: string;
+    startedAt: number;
+} & ({ state: "running" } | ({ state: "exited" } & ExitInfo));
+
+export function listGameProcesses(): Promise<SessionInfo[]> {
+    return invoke("game_process_list");
+}
+
+/** How much of a game log is kept in memory, older rows are moved to disk. `null` is unlimited */
+export type LogLimits = {
+    maxRows: number | null;
+    maxBytes: number | null;
+};
+
+/** Applies to the games started from now on */
+export function setLogLimits(limits: LogLimits): Promise<void> {
+    return invoke("game_process_set_log_limits", { limits });
+}
+
+/** How the logs of past sessions are kept on disk, for each game. `null` is unlimited */
+export type SessionLogPolicy = {
+    enabled: boolean;
+    /** The log goes on in a new file past this size */
+    maxFileBytes: number;
+    maxFiles: number | null;
+    maxTotalBytes: number | null;
+};
+
+export type SessionLogFile = {
+    name: string;
+    size: number;
+    /** Unix timestamp in seconds */
+    modified: number;
+};
+
+/** Applies to the games started from now on */
+export function setSessionLogPolicy(policy: SessionLogPolicy): Promise<void> {
+    return invoke("game_process_set_session_log_policy", { policy });
+}
+
+/** Newest first */
+export function listSessionLogs(titleId: string): Promise<SessionLogFile[]> {
+    return invoke("game_process_list_session_logs", { titleId });
+}
+
+export function openSessionLog(titleId: string, name: string): Promise<void> {
+    return invoke("game_process_open_session_log", { titleId, name });
+}
+
+export function deleteSessionLog(titleId: string, name: string): Promise<void> {
+    return invoke("game_process_delete_session_log", { titleId, name });
+}
+
+/** The enabled patch file and cheats are resolved by the backend from the game */
+export type LaunchProfile = {
+    emulator: string;
+    workDir?: string | null;
+    /** Replaces the default arguments */
+    args?: string[] | null;
+};
 
 export class GameProcess {
     #exe: string;
     #workingDir: string;
     #args: string[];
-    #pid: number;
+    #sessionId: SessionId;
     #ch: Channel<GameEvent>;
+    #pending: GameEvent[] | null = null;
 
     private constructor(
         exe: string,
         workingDir: string,
         args: string[],
-        pid: number,
+        sessionId: SessionId,
         ch: Channel<GameEvent>,
     ) {
         this.#exe = exe;
         this.#workingDir = workingDir;
         this.#args = args;
-        this.#pid = pid;
+        this.#sessionId = sessionId;
         this.#ch = ch;
     }
 
@@ -70,20 +223,64 @@ export class GameProcess {
         workingDir: string,
         args: string[],
         existingProcess: GameProcess | null = null,
+        gamePath: string | null = null,
     ): ResultAsync<GameProcess, GameStartError> {
         return ResultAsync.fromPromise(
             (async () => {
                 const ch = new Channel<GameEvent>();
-                const pid = await invoke<number>("game_process_spawn", {
-                    exe,
-                    wd: workingDir,
-                    args,
+                const sessionId = await invoke<SessionId>(
+                    "game_process_spawn",
+                    {
+                        exe,
+                        wd: workingDir,
+                        args,
+                        onEvent: ch,
+                        copyDataFrom: existingProcess?.sessionId ?? null,
+                        gamePath,
+                    },
+                );
+                return new GameProcess(exe, workingDir, args, sessionId, ch);
+            })(),
+            (err) => new GameStartError(exe, workingDir, args, err),
+        );
+    }
+
+    /** Starts the game through the backend launch pipeline, resolving after the IPC handshake */
+    static launch(
+        gameId: number,
+        profile: LaunchProfile,
+        existingProcess: GameProcess | null = null,
+    ): ResultAsync<GameProcess, GameStartError> {
+        const args = profile.args ?? [];
+        return ResultAsync.fromPromise(
+            (async () => {
+                // Events sent during the handshake are kept until a listener is set
+                const pending: GameEvent[] = [];
+                const ch = new Channel<GameEvent>();
+                ch.onmessage = (ev) => pending.push(ev);
+                const sessionId = await invoke<SessionId>("game_launch", {
+                    gameId,
+                    profile,
                     onEvent: ch,
-                    copyDataFromPid: existingProcess?.pid ?? null,
+                    copyDataFrom: existingProcess?.sessionId ?? null,
                 });
-                return new GameProcess(exe, workingDir, args, pid, ch);
+                const process = new GameProcess(
+                    profile.emulator,
+                    profile.workDir ?? "",
+                    args,
+                    sessionId,
+                    ch,
+                );
+                process.#pending = pending;
+                return process;
             })(),
-            (err) => new GameStartError(exe, workingDir, args, err),
+            (err) =>
+                new GameStartError(
+                    profile.emulator,
+                    profile.workDir ?? "",
+                    args,
+                    err,
+                ),
         );
     }
 
@@ -99,56 +296,144 @@ export class GameProcess {
         return this.#args;
     }
 
-    get pid() {
-        return this.#pid;
+    get sessionId() {
+        return this.#sessionId;
     }
 
     set onMessage(listener: (ev: GameEvent) => void) {
         this.#ch.onmessage = listener;
+        const pending = this.#pending;
+        this.#pending = null;
+        for (const ev of pending ?? []) {
+            listener(ev);
+        }
+    }
+
+    /** Replays the log from `fromRowId`, then sends the live events. Resolves to the unsubscribe function */
+    async subscribe(
+        listener: (ev: GameEvent) => void,
+        fromRowId = 0,
+    ): Promise<() => Promise<void>> {
+        const ch = new Channel<GameEvent>();
+        ch.onmessage = listener;
+        const subscriptionId = await invoke<SubscriptionId>(
+            "game_process_subscribe",
+            { sessionId: this.#sessionId, onEvent: ch, fromRowId },
+        );
+        return async () => {
+            await invoke("game_process_unsubscribe", {
+                sessionId: this.#sessionId,
+                subscriptionId,
+            });
+        };
     }
 
     async kill() {
-        await invoke("game_process_kill", { pid: this.pid });
+        await invoke("game_process_kill", { sessionId: this.#sessionId });
+    }
+
+    /** Sends STOP, then SIGTERM, then SIGKILL, waiting for the process to exit between each */
+    async stop({
+        ipcTimeoutMs,
+        termTimeoutMs,
+    }: {
+        ipcTimeoutMs?: number | undefined;
+        termTimeoutMs?: number | undefined;
+    } = {}) {
+        await invoke("game_process_stop", {
+            sessionId: this.#sessionId,
+            ipcTimeoutMs,
+            termTimeoutMs,
+        });
     }
 
     async delete() {
-        await invoke("game_process_delete", { pid: this.#pid });
+        await invoke("game_process_delete", { sessionId: this.#sessionId });
     }
 
-    async send(value: string) {
-        await invoke("game_process_send", { pid: this.pid, value });
+    async sendIpc(command: IpcCommand) {
+        await invoke("game_process_send_ipc", {
+            sessionId: this.#sessionId,
+            command,
+        });
     }
 
-    async getLog({
+    /** Reads a page of the log, forward from `fromRowId` or backward from it (the last row by default) */
+    getLog({
         level,
         logClass,
+        fromRowId,
+        limit,
+        direction,
     }: {
         level?: LogLevel[] | undefined;
         logClass?: string[] | undefined;
-    } = {}): Promise<LogEntry[]> {
-        return JSON.parse(
-            await invoke("game_process_get_log", {
-                pid: this.pid,
-                level,
-                logClass,
-            }),
-        );
+        fromRowId?: number | null | undefined;
+        limit?: number | undefined;
+        direction?: "forward" | "backward" | undefined;
+    } = {}): Promise<LogPage> {
+        return invoke("game_process_get_log", {
+            sessionId: this.#sessionId,
+            level,
+            logClass,
+            fromRowId,
+            limit,
+            direction,
+        });
+    }
+
+    searchLog(query: LogSearchQuery): Promise<LogSearchResult> {
+        return invoke("game_process_search_log", {
+            sessionId: this.#sessionId,
+            query,
+        });
     }
 
+    getLogClasses(): Promise<LogClass[]> {
+        return invoke("game_process_get_log_classes", {
+            sessionId: this.#sessionId,
+        });
+    }
+
+    /** The extension of `defaultName` is replaced with the one of the format */
     async saveLog({
         defaultName,
         level,
         logClass,
+        options,
     }: {
         defaultName: string;
         level?: LogLevel[] | undefined;
         logClass?: string[] | undefined;
+        options?: LogExportOptions | undefined;
     }) {
         await invoke("game_process_save_log", {
-            pid: this.pid,
+            sessionId: this.#sessionId,
             defaultName,
             level,
             logClass,
+            options,
+        });
+    }
+
+    /** Writes the log to `path` without a dialog. Returns the number of rows written */
+    exportLog({
+        path,
+        level,
+        logClass,
+        options,
+    }: {
+        path: string;
+        level?: LogLevel[] | undefined;
+        logClass?: string[] | undefined;
+        options?: LogExportOptions | undefined;
+    }): Promise<number> {
+        return invoke("game_process_export_log", {
+            sessionId: this.#sessionId,
+            path,
+            level,
+            logClass,
+            options,
         });
     }
 
@@ -163,17 +448,19 @@ export class GameProcess {
         patchMask = 0,
         patchSize = 0,
     ) {
-        return this.send(
-            "PATCH_MEMORY\n" +
-                `${modName}\n` +
-                `${offset}\n` +
-                `${value}\n` +
-                `${target}\n` +
-                `${size}\n` +
-                `${+isOffset}\n` +
-                `${+littleEndian}\n` +
-                `${patchMask}\n` +
-                `${patchSize}\n`,
-        );
+        return invoke("game_process_patch_memory", {
+            sessionId: this.#sessionId,
+            patch: {
+                modName,
+                offset,
+                value,
+                target,
+                size,
+                isOffset,
+                littleEndian,
+                patchMask,
+                patchSize,
+            } satisfies MemoryPatch,
+        });
     }
 }
@@ -0,0 +1,98 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { PSF } from "./psf";
+
+export type LibraryGame = {
+    path: string;
+    cusa: string;
+    title: string;
+    version: string;
+    fwVersion: string;
+    sfo: PSF | null;
+    /** The game is a package that is not installed */
+    package: boolean;
+};
+
+export function isPackagePath(path: string): boolean {
+    return path.toLowerCase().endsWith(".pkg");
+}
+
+export type LibraryEvent =
+    | { event: "discovered"; game: LibraryGame }
+    | { event: "removed"; path: string }
+    | { event: "error"; path: string; err: string }
+    | { event: "finished" };
+
+export async function scanLibrary(
+    roots: string[],
+    knownPaths: string[],
+    onEvent: (ev: LibraryEvent) => void,
+    { maxDepth }: { maxDepth?: number } = {},
+) {
+    const ch = new Channel<LibraryEvent>();
+    ch.onmessage = onEvent;
+    await invoke("library_scan", {
+        roots,
+        knownPaths,
+        maxDepth,
+        onEvent: ch,
+    });
+}
+
+export type LibraryWatchEvent =
+    | { event: "added"; game: LibraryGame }
+    | { event: "updated"; game: LibraryGame }
+    | { event: "removed"; path: string };
+
+export type LibraryRootSettings = {
+    maxDepth: number;
+    enabled: boolean;
+    readOnly: boolean;
+};
+
+export type LibraryRoot = LibraryRootSettings & {
+    id: number;
+    path: string;
+};
+
+export function listLibraryRoots(): Promise<LibraryRoot[]> {
+    return invoke("library_list_roots");
+}
+
+export function addLibraryRoot(
+    path: string,
+    settings?: Partial<LibraryRootSettings>,
+): Promise<LibraryRoot> {
+    return invoke("library_add_root", { path, settings });
+}
+
+export async function removeLibraryRoot(id: number) {
+    await invoke("library_remove_root", { id });
+}
+
+export async function rescanLibraryRoot(
+    id: number,
+    knownPaths: string[],
+    onEvent: (ev: LibraryEvent) => void,
+) {
+    const ch = new Channel<LibraryEvent>();
+    ch.onmessage = onEvent;
+    await invoke("library_rescan_root", { id, knownPaths, onEvent: ch });
+}
+
+/** Watches the enabled library roots */
+export async function watchLibrary(knownPaths: string[]) {
+    await invoke("library_watch", { knownPaths });
+}
+
+export async function unwatchLibrary() {
+    await invoke("library_unwatch");
+}
+
+export function listenLibraryWatch(
+    onEvent: (ev: LibraryWatchEvent) => void,
+): Promise<UnlistenFn> {
+    return listen<LibraryWatchEvent>("library://watch", (e) =>
+        onEvent(e.payload),
+    );
+}
@@ -0,0 +1,86 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import type { ParamSfoInfo } from "./psf";
+
+export interface PkgEntry {
+    id: number;
+    name: string | null;
+    offset: number;
+    size: number;
+    encrypted: boolean;
+}
+
+export interface PkgInfo {
+    contentId: string;
+    titleId: string | null;
+    contentType: number;
+    pkgSize: number;
+    paramSfo: ParamSfoInfo | null;
+    entries: PkgEntry[];
+}
+
+export async function readPkgInfo(path: string): Promise<PkgInfo> {
+    return await invoke("read_pkg_info", {
+        path,
+    });
+}
+
+export async function readPkgEntry(
+    path: string,
+    name: "param.sfo" | "icon0.png" | "pic0.png" | "pic1.png",
+): Promise<ArrayBuffer> {
+    return await invoke("read_pkg_entry", {
+        path,
+        name,
+    });
+}
+
+/** PEM files of the keys of fake-signed packages */
+export interface PkgKeyFiles {
+    derivedKey3: string;
+    fake: string;
+}
+
+export type InstallEvent =
+    | {
+          event: "started";
+          path: string;
+          totalBytes: number;
+          resumed: boolean;
+      }
+    | {
+          event: "progress";
+          current: string;
+          doneBytes: number;
+          totalBytes: number;
+      }
+    | { event: "finished"; path: string }
+    | { event: "cancelled" };
+
+/**
+ * @param keys Needed for fake-signed packages, their game files are encrypted
+ */
+export async function installPkg(
+    pkgPath: string,
+    gamesPath: string,
+    onEvent: (ev: InstallEvent) => void,
+    {
+        overwrite = false,
+        keys = null,
+    }: { overwrite?: boolean; keys?: PkgKeyFiles | null } = {},
+) {
+    const ch = new Channel<InstallEvent>();
+    ch.onmessage = onEvent;
+    await invoke("pkg_install", {
+        pkgPath,
+        gamesPath,
+        keys,
+        overwrite,
+        onEvent: ch,
+    });
+}
+
+export async function cancelPkgInstall(pkgPath: string) {
+    await invoke("pkg_install_cancel", {
+        pkgPath,
+    });
+}
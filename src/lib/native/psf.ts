@@ -21,3 +21,24 @@ export async function readPsf(path: string): Promise<PSF> {
         path,
     });
 }
+
+export interface ParamSfoInfo {
+    titleId: string | null;
+    title: string | null;
+    localizedTitles: { language: string; title: string }[];
+    appVer: string | null;
+    version: string | null;
+    category: string | { other: string } | null;
+    contentId: string | null;
+    systemVer: string | null;
+    attribute: { raw: number; flags: string[] } | null;
+    parentalLevel: number | null;
+    supportedLanguages: string[];
+    psf: PSF;
+}
+
+export async function readParamSfo(path: string): Promise<ParamSfoInfo> {
+    return await invoke("read_param_sfo", {
+        path,
+    });
+}
@@ -0,0 +1,66 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export interface TrpEntry {
+    name: string;
+    offset: number;
+    size: number;
+    flag: number;
+    encrypted: boolean;
+}
+
+export type TrophyGrade = "platinum" | "gold" | "silver" | "bronze" | "unknown";
+
+export interface Trophy {
+    id: number;
+    hidden: boolean;
+    grade: TrophyGrade;
+    parentId: number | null;
+    groupId: number | null;
+    name: string | null;
+    detail: string | null;
+    icon: string | null;
+}
+
+export interface TrophyGroup {
+    id: number;
+    name: string | null;
+    detail: string | null;
+}
+
+export interface TrophyConfig {
+    npCommId: string | null;
+    trophysetVersion: string | null;
+    titleName: string | null;
+    titleDetail: string | null;
+    groups: TrophyGroup[];
+    trophies: Trophy[];
+}
+
+export interface TrophyList {
+    entries: TrpEntry[];
+    config: TrophyConfig | null;
+}
+
+/**
+ * @param trophyKey `TrophyKey` of the emulator config. Retail games only have
+ * their trophy names and details in encrypted entries
+ */
+export async function readTrophies(
+    path: string,
+    trophyKey: string | null = null,
+): Promise<TrophyList> {
+    return await invoke("read_trophies", {
+        path,
+        trophyKey,
+    });
+}
+
+export async function readTrophyIcon(
+    path: string,
+    name: string,
+): Promise<ArrayBuffer> {
+    return await invoke("read_trophy_icon", {
+        path,
+        name,
+    });
+}
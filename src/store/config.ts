@@ -37,6 +37,16 @@ export const atomUserConfig = atom(
     },
 );
 
+/** Needed to read the trophies of retail games */
+export const atomTrophyKey = atom(async (get) => {
+    const keys = (await get(atomUserConfig))?.Keys;
+    if (typeof keys !== "object" || keys === null || !("TrophyKey" in keys)) {
+        return null;
+    }
+    const key = keys.TrophyKey;
+    return typeof key === "string" && key !== "" ? key : null;
+});
+
 export function refreshUserConfig(s: JotaiStore) {
     s.set(atomUserConfigReload, (prev) => prev + 1);
 }
@@ -13,9 +13,23 @@ export type GameEntry = {
     fw_version: string;
     sfo: PSF | null;
 
+    /** Total play time in seconds */
+    play_time: number;
+    /** Unix timestamp in seconds */
+    last_launched: number | null;
+    launch_count: number;
+    favourite: boolean;
+    notes: string | null;
+    tags: string[];
+
     error?: Error; // This is not stored in the database
 };
 
+export type GameStats = Pick<
+    GameEntry,
+    "play_time" | "last_launched" | "launch_count"
+>;
+
 export function isSameGame(g1: GameEntry, g2: GameEntry): boolean {
     return g1.cusa === g2.cusa && g1.version === g2.version;
 }
@@ -23,13 +37,24 @@ export function isSameGame(g1: GameEntry, g2: GameEntry): boolean {
 export const db = {
     conn,
     async listGames(): Promise<GameEntry[]> {
+        const tags = new Map<number, string[]>();
+        for (const { game_id, tag } of await conn.select<
+            { game_id: number; tag: string }[]
+        >("SELECT game_id, tag FROM game_tags ORDER BY tag")) {
+            tags.set(game_id, [...(tags.get(game_id) ?? []), tag]);
+        }
         return (
             await conn.select<
-                (Omit<GameEntry, "sfo"> & { sfo_json: string })[]
+                (Omit<GameEntry, "sfo" | "favourite" | "tags"> & {
+                    sfo_json: string;
+                    favourite: number;
+                })[]
             >("SELECT * FROM games")
-        ).map(({ sfo_json, ...rest }) => ({
+        ).map(({ sfo_json, favourite, ...rest }) => ({
             ...rest,
             sfo: sfo_json ? JSON.parse(sfo_json) : null,
+            favourite: favourite !== 0,
+            tags: tags.get(rest.id) ?? [],
         }));
     },
     async removeGame(path: string): Promise<void> {
@@ -58,4 +83,45 @@ export const db = {
             id: r.lastInsertId,
         };
     },
+    async updateGame(data: GameEntry): Promise<void> {
+        await conn.execute(
+            "UPDATE games SET cusa = $2, title = $3, version = $4, fw_version = $5, sfo_json = $6 WHERE path = $1",
+            [
+                data.path,
+                data.cusa,
+                data.title,
+                data.version,
+                data.fw_version,
+                data.sfo ? JSON.stringify(data.sfo) : null,
+            ],
+        );
+    },
+    async getStats(id: number): Promise<GameStats | undefined> {
+        const rows = await conn.select<GameStats[]>(
+            "SELECT play_time, last_launched, launch_count FROM games WHERE id = $1",
+            [id],
+        );
+        return rows[0];
+    },
+    async setFavourite(id: number, favourite: boolean): Promise<void> {
+        await conn.execute("UPDATE games SET favourite = $2 WHERE id = $1", [
+            id,
+            favourite ? 1 : 0,
+        ]);
+    },
+    async setNotes(id: number, notes: string | null): Promise<void> {
+        await conn.execute("UPDATE games SET notes = $2 WHERE id = $1", [
+            id,
+            notes,
+        ]);
+    },
+    async setTags(id: number, tags: string[]): Promise<void> {
+        await conn.execute("DELETE FROM game_tags WHERE game_id = $1", [id]);
+        for (const tag of new Set(tags)) {
+            await conn.execute(
+                "INSERT INTO game_tags (game_id, tag) VALUES ($1, $2)",
+                [id, tag],
+            );
+        }
+    },
 } as const;
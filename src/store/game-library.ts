@@ -1,11 +1,21 @@
-import { basename, join, sep } from "@tauri-apps/api/path";
-import { exists, mkdir, readDir, stat, watch } from "@tauri-apps/plugin-fs";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { sep } from "@tauri-apps/api/path";
+import { exists, mkdir } from "@tauri-apps/plugin-fs";
 import { atom } from "jotai";
 import { toast } from "sonner";
-import { readPsf } from "@/lib/native/psf";
+import {
+    addLibraryRoot,
+    type LibraryGame,
+    type LibraryRoot,
+    type LibraryRootSettings,
+    listenLibraryWatch,
+    listLibraryRoots,
+    removeLibraryRoot,
+    rescanLibraryRoot,
+    watchLibrary,
+} from "@/lib/native/library";
 import { stringifyError } from "@/lib/utils/error";
 import { atomWithTauriStore } from "@/lib/utils/jotai/tauri-store";
-import type { Callback } from "@/lib/utils/types";
 import { defaultStore } from ".";
 import type { CUSA, Version } from "./common";
 import { db, type GameEntry } from "./db";
@@ -25,93 +35,106 @@ export const atomGameLibrarySorting = atomWithTauriStore<SortType>(
 
 export const atomGameLibraryIsIndexing = atom(false);
 export const atomGameLibrary = atom<GameEntry[]>([]);
+export const atomLibraryRoots = atom<LibraryRoot[]>([]);
 
-async function loadGameData(path: string): Promise<GameEntry> {
-    try {
-        const base = await basename(path);
-
-        const paramSfo = await join(path, "sce_sys", "param.sfo");
-
-        if (!(await exists(paramSfo))) {
-            return {
-                id: -1,
-                path: path,
-                cusa: `N/A - ${base}` as CUSA,
-                title: base,
-                version: "N/A",
-                fw_version: "N/A",
-                sfo: null,
-            };
-        }
-
-        const sfo = await readPsf(paramSfo);
-        const e = sfo.entries;
+const knownPaths = new Set<string>();
 
-        let fw_version = e.SYSTEM_VER?.Integer?.toString(16)
-            .padStart(8, "0")
-            .slice(0, 4);
-        if (fw_version) {
-            fw_version = `${fw_version.slice(0, 2).trimStart()}.${fw_version.slice(2)}`;
-            if (fw_version.startsWith("0")) {
-                fw_version = fw_version.slice(1);
-            }
-        }
-
-        return {
-            id: -1,
-            path: path,
-            cusa: (e.TITLE_ID?.Text || base) as CUSA,
-            title: e.TITLE?.Text || "Unknown",
-            version: (e.APP_VER?.Text as Version) || "N/A",
-            fw_version: fw_version || "UNK",
-            sfo,
-        };
-    } catch (e: unknown) {
-        console.error(`could not read game info at: "${path}"`, e);
-        return {
-            id: -2,
-            path: path,
-            cusa: "N/A",
-            title: "N/A",
-            version: "N/A",
-            fw_version: "N/A",
-            sfo: null,
-            error: new Error(`game read info. ${stringifyError(e)}`, {
-                cause: e,
-            }),
-        };
-    }
+function toGameEntry(game: LibraryGame): GameEntry {
+    return {
+        id: -1,
+        path: game.path,
+        cusa: game.cusa as CUSA,
+        title: game.title,
+        version: game.version as Version,
+        fw_version: game.fwVersion,
+        sfo: game.sfo,
+        play_time: 0,
+        last_launched: null,
+        launch_count: 0,
+        favourite: false,
+        notes: null,
+        tags: [],
+    };
 }
 
-const gameRegisterQueue: string[] = [];
+const gameRegisterQueue: GameEntry[] = [];
 let gameRegisterQueueIsUse = false;
 
-async function registerGamePath(workPath: string) {
-    console.debug(`Loading game from ${workPath}`);
-    gameRegisterQueue.push(workPath);
+async function registerGame(entry: GameEntry) {
+    console.debug(`Loading game from ${entry.path}`);
+    gameRegisterQueue.push(entry);
     if (gameRegisterQueueIsUse) {
         return;
     }
     gameRegisterQueueIsUse = true;
     while (gameRegisterQueue.length > 0) {
-        const path = gameRegisterQueue.shift();
-        if (!path) {
+        const game = gameRegisterQueue.shift();
+        if (!game) {
             break;
         }
-        let gameData = await loadGameData(path);
-        if (gameData.id === -1) {
-            if (!("error" in gameData)) {
-                gameData = await db.addGame(gameData);
-            }
-            defaultStore.set(atomGameLibrary, (prev) =>
-                prev.filter((e) => e.path !== path).concat(gameData),
-            );
-        }
+        const gameData = await db.addGame(game).catch((e: unknown) => {
+            console.error(`could not save game at: "${game.path}"`, e);
+            return game;
+        });
+        defaultStore.set(atomGameLibrary, (prev) =>
+            prev.filter((e) => e.path !== gameData.path).concat(gameData),
+        );
     }
     gameRegisterQueueIsUse = false;
 }
 
-function unregisterGamePathPrefix(pathPrefix: string, knownPaths: Set<string>) {
+async function updateGame(entry: GameEntry) {
+    console.debug(`Reloading game from ${entry.path}`);
+    await db.updateGame(entry).catch((e: unknown) => {
+        console.error(`could not update game at: "${entry.path}"`, e);
+    });
+    defaultStore.set(atomGameLibrary, (prev) =>
+        prev.map((e) =>
+            e.path === entry.path
+                ? {
+                      ...e,
+                      cusa: entry.cusa,
+                      title: entry.title,
+                      version: entry.version,
+                      fw_version: entry.fw_version,
+                      sfo: entry.sfo,
+                  }
+                : e,
+        ),
+    );
+}
+
+function patchGame(id: number, patch: Partial<GameEntry>) {
+    defaultStore.set(atomGameLibrary, (prev) =>
+        prev.map((e) => (e.id === id ? { ...e, ...patch } : e)),
+    );
+}
+
+export async function setGameFavourite(id: number, favourite: boolean) {
+    await db.setFavourite(id, favourite);
+    patchGame(id, { favourite });
+}
+
+export async function setGameNotes(id: number, notes: string | null) {
+    await db.setNotes(id, notes);
+    patchGame(id, { notes });
+}
+
+export async function setGameTags(id: number, tags: string[]) {
+    const unique = [...new Set(tags)].sort();
+    await db.setTags(id, unique);
+    patchGame(id, { tags: unique });
+}
+
+/** The play time and launch count are updated by the backend when a game starts and exits */
+export async function refreshGameStats(id: number) {
+    const stats = await db.getStats(id);
+    if (stats) {
+        patchGame(id, stats);
+    }
+}
+
+function unregisterGamePathPrefix(pathPrefix: string) {
     defaultStore.set(atomGameLibrary, (prev) =>
         prev.filter((e) => {
             const toRemove = e.path.startsWith(pathPrefix);
@@ -124,171 +147,164 @@ function unregisterGamePathPrefix(pathPrefix: string, knownPaths: Set<string>) {
     );
 }
 
-async function isGame(path: string) {
-    const eBootPath = await join(path, "eboot.bin");
-    return await exists(eBootPath);
+async function scanRoot(root: LibraryRoot) {
+    try {
+        await rescanLibraryRoot(root.id, [...knownPaths], (ev) => {
+            switch (ev.event) {
+                case "discovered":
+                    knownPaths.add(ev.game.path);
+                    void registerGame(toGameEntry(ev.game));
+                    break;
+                case "removed":
+                    unregisterGamePathPrefix(ev.path);
+                    break;
+                case "error":
+                    console.error(
+                        `Error discovering game at "${ev.path}"`,
+                        ev.err,
+                    );
+                    break;
+                case "finished":
+                    break;
+                default: {
+                    // exaustive switch
+                    const a: never = ev;
+                    return a;
+                }
+            }
+        });
+    } catch (e: unknown) {
+        console.error(`Error discovering games at "${root.path}"`, e);
+    }
+}
+
+async function refreshRoots() {
+    const roots = await listLibraryRoots();
+    defaultStore.set(atomLibraryRoots, roots);
+    return roots;
 }
 
-async function scanDirectory(
+export async function addGameRoot(
     path: string,
-    knownPaths: Set<string>,
-    unknownPaths: Set<string>,
-    signal: AbortSignal,
-    recursionLevel: number,
+    settings?: Partial<LibraryRootSettings>,
 ) {
+    const root = await addLibraryRoot(path, settings);
+    await refreshRoots();
+    defaultStore.set(atomGameLibraryIsIndexing, true);
     try {
-        if (recursionLevel > 3 || signal.aborted) {
-            return;
-        }
-        if (knownPaths.has(path)) {
-            unknownPaths.delete(path);
-            return;
-        }
-        if (path.endsWith("-UPDATE") || path.endsWith("-patch")) {
-            return;
-        }
-        if (await isGame(path)) {
-            void registerGamePath(path);
-            return;
-        }
-        const children = await readDir(path);
+        await scanRoot(root);
+    } finally {
+        defaultStore.set(atomGameLibraryIsIndexing, false);
+    }
+    return root;
+}
+
+export async function removeGameRoot(id: number) {
+    const root = defaultStore.get(atomLibraryRoots).find((r) => r.id === id);
+    await removeLibraryRoot(id);
+    if (root) {
+        unregisterGamePathPrefix(root.path + sep());
+    }
+    await refreshRoots();
+}
+
+export async function rescanGameRoot(id: number) {
+    const root = defaultStore.get(atomLibraryRoots).find((r) => r.id === id);
+    if (!root) {
+        return;
+    }
+    defaultStore.set(atomGameLibraryIsIndexing, true);
+    try {
+        await scanRoot(root);
+    } finally {
+        defaultStore.set(atomGameLibraryIsIndexing, false);
+    }
+}
+
+let unlistenWatch: UnlistenFn | null = null;
 
-        for (const c of children) {
-            if (c.isDirectory) {
-                const childPath = await join(path, c.name);
-                await scanDirectory(
-                    childPath,
-                    knownPaths,
-                    unknownPaths,
-                    signal,
-                    recursionLevel + 1,
-                );
+/** Replaces the previous listener, if any */
+async function listenWatch() {
+    unlistenWatch?.();
+    unlistenWatch = null;
+    unlistenWatch = await listenLibraryWatch((ev) => {
+        switch (ev.event) {
+            case "added":
+                knownPaths.add(ev.game.path);
+                void registerGame(toGameEntry(ev.game));
+                break;
+            case "updated":
+                void updateGame(toGameEntry(ev.game));
+                break;
+            case "removed":
+                unregisterGamePathPrefix(ev.path);
+                break;
+            default: {
+                // exaustive switch
+                const a: never = ev;
+                return a;
             }
         }
-    } catch (e: unknown) {
-        console.error(`Error discovering game at "${path}"`, e);
-    }
+    });
 }
 
 (async () => {
     const cachedGames = await db.listGames();
     defaultStore.set(atomGameLibrary, cachedGames);
 
-    const knownPaths = new Set<string>();
-
     for (const e of cachedGames) {
         knownPaths.add(e.path);
     }
 
+    await listenWatch();
+
     let prevPath: string | null = null;
-    let cancel: (() => Promise<void>) | undefined;
 
-    const onChange = (path: string) => {
-        const c = cancel;
-        let unsub: Callback | undefined;
-        const abortController = new AbortController();
-        const signal = abortController.signal;
-        const prom = (async () => {
-            if (c) {
-                await c();
+    // The games path is the default root, where games are installed
+    const changeGamesPath = async (path: string) => {
+        try {
+            if (!path || path === prevPath) {
+                return;
             }
-            try {
-                if (!path || path === prevPath) {
-                    return;
-                }
-                console.log("Indexing games at", path);
-                if (prevPath != null) {
-                    prevPath = path; // set this before await
-                    defaultStore.set(atomGameLibraryIsIndexing, true);
-                    defaultStore.set(atomGameLibrary, []);
-                    knownPaths.clear();
-                    await db.removeAllGames();
-                }
-                prevPath = path;
-                if (path) {
-                    if (!(await exists(path))) {
-                        await mkdir(path, { recursive: true });
-                    }
-                    if (signal.aborted) {
-                        return;
-                    }
-                    const unknownPaths = new Set<string>(knownPaths);
-                    await scanDirectory(
-                        path,
-                        knownPaths,
-                        unknownPaths,
-                        signal,
-                        0,
-                    );
-                    if (signal.aborted) {
-                        return;
+            console.log("Indexing games at", path);
+            defaultStore.set(atomGameLibraryIsIndexing, true);
+            if (!(await exists(path))) {
+                await mkdir(path, { recursive: true });
+            }
+            const roots = await refreshRoots();
+            const isFirstLoad = prevPath == null;
+            const prevRoot = roots.find((r) => r.path === prevPath);
+            prevPath = path;
+            if (prevRoot) {
+                await removeGameRoot(prevRoot.id);
+            }
+            if (!roots.some((r) => r.path === path)) {
+                await scanRoot(await addLibraryRoot(path));
+                await refreshRoots();
+            }
+            if (isFirstLoad) {
+                for (const root of roots) {
+                    if (root.enabled) {
+                        await scanRoot(root);
                     }
-                    unknownPaths.forEach((path) => {
-                        unregisterGamePathPrefix(path, knownPaths);
-                    });
-                    unsub = await watch(path, async (e) => {
-                        if (typeof e.type === "object") {
-                            if ("create" in e.type) {
-                                const newPath = e.paths[0];
-                                if (
-                                    newPath &&
-                                    (await stat(newPath)).isDirectory
-                                ) {
-                                    let idx = Number.POSITIVE_INFINITY;
-                                    while (true) {
-                                        idx = newPath.lastIndexOf(
-                                            sep(),
-                                            idx - 1,
-                                        );
-                                        if (idx === -1) {
-                                            break;
-                                        }
-                                        if (
-                                            knownPaths.has(
-                                                newPath.slice(0, idx),
-                                            )
-                                        ) {
-                                            return;
-                                        }
-                                    }
-                                    defaultStore.set(
-                                        atomGameLibraryIsIndexing,
-                                        true,
-                                    );
-                                    await scanDirectory(
-                                        newPath,
-                                        knownPaths,
-                                        signal,
-                                        1,
-                                    );
-                                    defaultStore.set(
-                                        atomGameLibraryIsIndexing,
-                                        false,
-                                    );
-                                }
-                            } else if ("remove" in e.type) {
-                                const newPath = e.paths[0];
-                                if (newPath) {
-                                    unregisterGamePathPrefix(
-                                        newPath,
-                                        knownPaths,
-                                    );
-                                }
-                            }
-                        }
-                    });
-                    defaultStore.set(atomGameLibraryIsIndexing, false);
                 }
-            } catch (e: unknown) {
-                console.error("error watching path", stringifyError(e));
-                toast.error(`Error watching games path: ${stringifyError(e)}`);
+                await watchLibrary([...knownPaths]);
             }
-        })();
-        cancel = () => {
-            abortController.abort();
-            unsub?.();
-            return prom;
-        };
+        } catch (e: unknown) {
+            console.error("error watching path", stringifyError(e));
+            toast.error(`Error watching games path: ${stringifyError(e)}`);
+        } finally {
+            defaultStore.set(atomGameLibraryIsIndexing, false);
+        }
     };
-    atomGamesPath.listen(onChange);
+
+    let pending = Promise.resolve();
+    atomGamesPath.listen((path) => {
+        pending = pending.then(() => changeGamesPath(path));
+    });
 })();
+
+import.meta.hot?.dispose(() => {
+    unlistenWatch?.();
+    unlistenWatch = null;
+});
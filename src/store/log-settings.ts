@@ -0,0 +1,15 @@
+import type { LogLimits } from "@/lib/native/game-process";
+import { atomWithTauriStore } from "@/lib/utils/jotai/tauri-store";
+
+/** Same as the backend defaults */
+export const DEFAULT_LOG_LIMITS: LogLimits = {
+    maxRows: 100_000,
+    maxBytes: 64 * 1024 * 1024,
+};
+
+/** Rows of the game log kept in memory, older ones are moved to disk */
+export const atomLogLimits = atomWithTauriStore<LogLimits>(
+    "config.json",
+    "log_limits",
+    { initialValue: DEFAULT_LOG_LIMITS },
+);
@@ -1,5 +1,6 @@
 import { join, appDataDir as pAppDataDir } from "@tauri-apps/api/path";
 import { atom } from "jotai";
+import type { PkgKeyFiles } from "@/lib/native/pkg";
 import { atomWithTauriStore } from "@/lib/utils/jotai/tauri-store";
 
 const appDataDir = pAppDataDir();
@@ -35,3 +36,10 @@ export const atomPatchPath = atom(async () =>
 );
 
 export const atomCheatPath = atom(async () => join(await appDataDir, "cheats"));
+
+/** Keys to install fake-signed packages, provided by the user */
+export const atomPkgKeyFiles = atomWithTauriStore<PkgKeyFiles | null>(
+    "config.json",
+    "pkg_key_files",
+    { initialValue: null },
+);
@@ -1,6 +1,5 @@
 import { atom, type PrimitiveAtom } from "jotai";
-import type { GameProcess, LogEntry } from "@/lib/native/game-process";
-import type { Callback } from "@/lib/utils/types";
+import type { GameProcess } from "@/lib/native/game-process";
 import { defaultStore, type JotaiStore } from ".";
 import type { GameEntry } from "./db";
 
@@ -13,7 +12,6 @@ export type GameProcessState = {
     atomRunning: PrimitiveAtom<true | number>; // true or exit code
     atomError: PrimitiveAtom<string | null>;
     log: {
-        atomCallback: PrimitiveAtom<Callback<[LogEntry]>[]>;
         atomClassList: PrimitiveAtom<string[]>;
     };
     atomCapabilities: PrimitiveAtom<Capabilities[]>;
@@ -29,7 +27,6 @@ export function createGameProcesState(
     const atomProcess = atom(process);
     const atomRunning = atom<true | number>(true);
     const atomError = atom<string | null>(null);
-    const atomLogCallback = atom<Callback<[LogEntry]>[]>([]);
     const atomLogClassList = atom<string[]>(["STDERR"]);
     const atomCapabilities = atom<Capabilities[]>([]);
 
@@ -40,7 +37,6 @@ export function createGameProcesState(
         atomRunning,
         atomError,
         log: {
-            atomCallback: atomLogCallback,
             atomClassList: atomLogClassList,
         },
         atomCapabilities,